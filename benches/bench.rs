@@ -1,13 +1,57 @@
+use std::fmt::Write;
 use std::fs::read_to_string;
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use pddl_parser::domain::domain::Domain;
+use pddl_parser::plan::plan::Plan;
+use pddl_parser::problem::Problem;
+
+/// Generate a synthetic domain with `n` predicates and `n` actions, each action using a few of the predicates.
+fn synthetic_domain(n: usize) -> String {
+    let mut domain = String::from("(define (domain synthetic)\n(:requirements :strips :typing)\n(:types item)\n");
+    domain.push_str("(:predicates\n");
+    for i in 0..n {
+        writeln!(domain, "(p{i} ?a - item ?b - item)").unwrap();
+    }
+    domain.push_str(")\n");
+    for i in 0..n {
+        let next = (i + 1) % n;
+        writeln!(
+            domain,
+            "(:action a{i}\n:parameters (?a - item ?b - item)\n:precondition (and (p{i} ?a ?b) (not (p{next} ?a ?b)))\n:effect (and (not (p{i} ?a ?b)) (p{next} ?a ?b))\n)"
+        )
+        .unwrap();
+    }
+    domain.push(')');
+    domain
+}
 
 fn bench(c: &mut Criterion) {
     let domain_file = read_to_string("tests/domain.pddl").unwrap();
     c.bench_function("Domain::parse", |b| {
         b.iter(|| Domain::parse(domain_file.as_str().into()));
     });
+
+    let durative_domain_file = read_to_string("tests/durative-actions-domain.pddl").unwrap();
+    c.bench_function("Domain::parse (durative actions)", |b| {
+        b.iter(|| Domain::parse(durative_domain_file.as_str().into()));
+    });
+
+    let synthetic_domain_file = synthetic_domain(500);
+    assert!(Domain::parse(synthetic_domain_file.as_str().into()).is_ok());
+    c.bench_function("Domain::parse (synthetic, 500 actions)", |b| {
+        b.iter(|| Domain::parse(synthetic_domain_file.as_str().into()));
+    });
+
+    let problem_file = read_to_string("tests/problem.pddl").unwrap();
+    c.bench_function("Problem::parse", |b| {
+        b.iter(|| Problem::parse(problem_file.as_str().into()));
+    });
+
+    let plan_file = read_to_string("tests/durative-plan.txt").unwrap();
+    c.bench_function("Plan::parse", |b| {
+        b.iter(|| Plan::parse(plan_file.as_str().into()));
+    });
 }
 
 criterion_group!(benches, bench);
@@ -38,7 +38,7 @@ fn main() {
         let domain_str = std::fs::read_to_string(domain_file).unwrap();
         let d = Domain::parse(domain_str.as_str().into());
         if let Err(e) = d {
-            log::error!("Domain Error: {:?}", e);
+            log::error!("Domain Error: {}", e);
         }
     }
 
@@ -47,7 +47,7 @@ fn main() {
         let problem_str = std::fs::read_to_string(problem_file).unwrap();
         let p = Problem::parse(problem_str.as_str().into());
         if let Err(e) = p {
-            log::error!("Problem Error: {:?}", e);
+            log::error!("Problem Error: {}", e);
         }
     }
 
@@ -56,7 +56,7 @@ fn main() {
         let plan_str = std::fs::read_to_string(plan_file).unwrap();
         let p = Plan::parse(plan_str.as_str().into());
         if let Err(e) = p {
-            log::error!("Plan Error: {:?}", e);
+            log::error!("Plan Error: {}", e);
         }
     }
 }
@@ -0,0 +1,41 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::domain::domain::Domain;
+use crate::error::ParserError;
+
+/// Hash the source of a PDDL file. This is the key used by [`parse_cached`].
+pub fn source_hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parse a domain, reusing a previously parsed domain if the same source has already been parsed.
+///
+/// The cache is keyed by the hash of the source (see [`source_hash`]). On a hit, a clone of the cached domain is returned. On a miss, the source is parsed and the result is inserted into the cache. Errors are not cached.
+///
+/// # Errors
+///
+/// Returns an error if the source is not in the cache and it fails to parse.
+pub fn parse_cached(source: &str, cache: &mut HashMap<u64, Domain>) -> Result<Domain, ParserError> {
+    parse_cached_with(source, cache, |source| Domain::parse(source.into()))
+}
+
+pub(crate) fn parse_cached_with<F>(
+    source: &str,
+    cache: &mut HashMap<u64, Domain>,
+    parse: F,
+) -> Result<Domain, ParserError>
+where
+    F: FnOnce(&str) -> Result<Domain, ParserError>,
+{
+    let key = source_hash(source);
+    if let Some(domain) = cache.get(&key) {
+        return Ok(domain.clone());
+    }
+    let domain = parse(source)?;
+    cache.insert(key, domain.clone());
+    Ok(domain)
+}
@@ -9,6 +9,18 @@ use super::simple_action::SimpleAction;
 use crate::domain::typed_parameter::TypedParameter;
 use crate::error::ParserError;
 use crate::lexer::TokenStream;
+use crate::pretty::PrettyOptions;
+
+/// The number of effects of an action, by kind. See [`Action::effect_summary`].
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EffectSummary {
+    /// The number of positive literals, i.e. the atoms added by the action.
+    pub adds: usize,
+    /// The number of negated literals, i.e. the atoms deleted by the action.
+    pub deletes: usize,
+    /// The number of numeric effects (`assign`, `increase`, `decrease`, `scale-up` and `scale-down`).
+    pub numeric: usize,
+}
 
 /// Enum to represent either an `Action` or a `DurativeAction`.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -40,6 +52,38 @@ impl Action {
         }
     }
 
+    /// Get the simple action, or `None` if the action is a durative action.
+    pub fn as_simple(&self) -> Option<&SimpleAction> {
+        match self {
+            Self::Simple(action) => Some(action),
+            Self::Durative(_) => None,
+        }
+    }
+
+    /// Get the simple action mutably, or `None` if the action is a durative action.
+    pub fn as_simple_mut(&mut self) -> Option<&mut SimpleAction> {
+        match self {
+            Self::Simple(action) => Some(action),
+            Self::Durative(_) => None,
+        }
+    }
+
+    /// Get the durative action, or `None` if the action is a simple action.
+    pub fn as_durative(&self) -> Option<&DurativeAction> {
+        match self {
+            Self::Simple(_) => None,
+            Self::Durative(action) => Some(action),
+        }
+    }
+
+    /// Get the durative action mutably, or `None` if the action is a simple action.
+    pub fn as_durative_mut(&mut self) -> Option<&mut DurativeAction> {
+        match self {
+            Self::Simple(_) => None,
+            Self::Durative(action) => Some(action),
+        }
+    }
+
     /// Get the parameters of the action. This is the same as the parameters of the simple or durative action.
     pub fn parameters(&self) -> &[TypedParameter] {
         match self {
@@ -64,6 +108,54 @@ impl Action {
         }
     }
 
+    /// Get the cost of the action when it is constant, i.e. the sum of the `(increase (total-cost) N)` effects of the action, where `N` is a number (integer or floating point). An action that does not increase `total-cost` has a cost of `0`.
+    ///
+    /// Returns `None` if the cost depends on the parameters or on the state, e.g. `(increase (total-cost) (distance ?from ?to))`, or if `total-cost` is increased inside a quantified or conditional effect.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn constant_cost(&self) -> Option<f64> {
+        fn cost(expression: &Expression, quantified: bool) -> Option<f64> {
+            match expression {
+                Expression::Increase(target, value)
+                    if matches!(&**target, Expression::Atom { name, .. } if name == "total-cost") =>
+                {
+                    match &**value {
+                        Expression::Number(n) if !quantified => Some(*n as f64),
+                        Expression::Float(f) if !quantified => Some(f.0),
+                        _ => None,
+                    }
+                },
+                Expression::Forall(_, expression) | Expression::When(_, expression) => cost(expression, true),
+                _ => expression.children().into_iter().map(|child| cost(child, quantified)).sum(),
+            }
+        }
+
+        cost(&self.effect(), false)
+    }
+
+    /// Count the positive literals, negated literals and numeric effects of the action. The effect is flattened first, so the effects inside conjunctions, quantified effects, conditional effects and timed effects are counted too.
+    pub fn effect_summary(&self) -> EffectSummary {
+        fn count(expression: &Expression, summary: &mut EffectSummary) {
+            match expression {
+                Expression::Atom { .. } => summary.adds += 1,
+                Expression::Not(expression) if matches!(&**expression, Expression::Atom { .. }) => summary.deletes += 1,
+                Expression::Assign(..)
+                | Expression::Increase(..)
+                | Expression::Decrease(..)
+                | Expression::ScaleUp(..)
+                | Expression::ScaleDown(..) => summary.numeric += 1,
+                Expression::And(expressions) => expressions.iter().for_each(|expression| count(expression, summary)),
+                Expression::Forall(_, expression)
+                | Expression::When(_, expression)
+                | Expression::Duration(_, expression) => count(expression, summary),
+                _ => {},
+            }
+        }
+
+        let mut summary = EffectSummary::default();
+        count(&self.effect(), &mut summary);
+        summary
+    }
+
     /// Parse an action from a token stream.
     pub fn parse(input: TokenStream) -> IResult<TokenStream, Action, ParserError> {
         alt((
@@ -79,4 +171,12 @@ impl Action {
             Self::Durative(action) => action.to_pddl(),
         }
     }
+
+    /// Convert the action to PDDL, using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
+        match self {
+            Self::Simple(action) => action.to_pddl_with_options(options),
+            Self::Durative(action) => action.to_pddl_with_options(options),
+        }
+    }
 }
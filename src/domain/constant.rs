@@ -1,12 +1,13 @@
 use nom::multi::{many0, many1};
-use nom::sequence::{delimited, preceded, separated_pair};
+use nom::sequence::{delimited, pair, preceded, separated_pair};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
+use super::requirement::Requirement;
 use super::typing::Type;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::tokens::{id, requires};
 
 /// A constant with a type.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -26,7 +27,7 @@ impl Constant {
             Token::OpenParen,
             preceded(
                 Token::Constants,
-                many0(separated_pair(many1(id), Token::Dash, Type::parse_type)),
+                many0(separated_pair(many1(id), pair(Token::Dash, requires(&[Requirement::Typing])), Type::parse_type)),
             ),
             Token::CloseParen,
         )(input)?;
@@ -43,8 +44,10 @@ impl Constant {
         Ok((output, constants))
     }
 
-    /// Convert the constant to PDDL.
+    /// Convert the constant to PDDL, as an entry of the `:constants` section (e.g. `kitchen - location`).
+    ///
+    /// The constant is not wrapped in parentheses: `(:constants (kitchen - location))` is not valid PDDL, so such an output could not be parsed back.
     pub fn to_pddl(&self) -> String {
-        format!("({} - {})", self.name, self.type_.to_pddl())
+        format!("{} - {}", self.name, self.type_.to_pddl())
     }
 }
@@ -0,0 +1,47 @@
+use nom::sequence::{delimited, pair, preceded};
+use nom::IResult;
+use serde::{Deserialize, Serialize};
+
+use super::expression::Expression;
+use super::requirement::Requirement;
+use super::typed_predicate::TypedPredicate;
+use crate::error::ParserError;
+use crate::lexer::{Token, TokenStream};
+use crate::pretty::PrettyOptions;
+use crate::tokens::requires;
+
+/// A derived predicate (PDDL 2.2), i.e. a predicate whose truth value is defined by a formula over other predicates.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DerivedPredicate {
+    /// The head of the derived predicate, with its typed parameters.
+    pub predicate: TypedPredicate,
+    /// The formula defining when the derived predicate holds.
+    pub body: Expression,
+}
+
+impl DerivedPredicate {
+    /// Parse a derived predicate from a token stream, e.g. `(:derived (reachable ?x - loc) (connected ?x ?y))`.
+    pub fn parse_derived(input: TokenStream) -> IResult<TokenStream, DerivedPredicate, ParserError> {
+        log::debug!("BEGIN > parse_derived {:?}", input.span());
+        let (output, (predicate, body)) = delimited(
+            Token::OpenParen,
+            preceded(
+                pair(Token::Derived, requires(&[Requirement::DerivedPredicates])),
+                pair(TypedPredicate::parse_typed_predicate, Expression::parse_expression),
+            ),
+            Token::CloseParen,
+        )(input)?;
+        log::debug!("END < parse_derived {:?}", output.span());
+        Ok((output, DerivedPredicate { predicate, body }))
+    }
+
+    /// Convert the derived predicate to PDDL.
+    pub fn to_pddl(&self) -> String {
+        self.to_pddl_with_options(&PrettyOptions::default())
+    }
+
+    /// Convert the derived predicate to PDDL, using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
+        format!("(:derived {}\n{}\n)", self.predicate.to_pddl(), options.expression(&self.body))
+    }
+}
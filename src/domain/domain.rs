@@ -1,24 +1,40 @@
-use nom::combinator::opt;
+use std::collections::{BTreeSet, HashMap};
+
+use nom::branch::alt;
+use nom::combinator::{map, opt, peek};
 use nom::multi::many0;
-use nom::sequence::{delimited, preceded, tuple};
+use nom::sequence::{delimited, pair, preceded, tuple};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
-use super::action::Action;
+use super::action::{Action, EffectSummary};
 use super::constant::Constant;
+use super::derived::DerivedPredicate;
+use super::durative_action::DurativeAction;
+use super::expression::{BinaryOp, DurationInstant, Expression};
+use super::parameter::Parameter;
 use super::requirement::Requirement;
-use super::typed_predicate::TypedPredicate;
+use super::simple_action::SimpleAction;
+use super::typed_predicate::{PredicateSchema, TypedPredicate};
 use super::typedef::TypeDef;
 use super::typing::Type;
+use super::validate::ValidationError;
+use super::visitor::DomainVisitor;
 use crate::error::ParserError;
-use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::lexer::{escape, Token, TokenStream};
+use crate::options::ParseOptions;
+use crate::pretty::PrettyOptions;
+use crate::problem::Problem;
+use crate::tokens::{id, lenient, parse_description, requires, skip_balanced};
 
 /// A PDDL domain.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Domain {
     /// The name of the domain.
     pub name: String,
+    /// The description of the domain, from the optional `(:description "...")` section.
+    #[serde(default)]
+    pub description: Option<String>,
     /// The requirements of the domain.
     pub requirements: Vec<Requirement>,
     /// The types of the domain.
@@ -31,22 +47,171 @@ pub struct Domain {
     pub functions: Vec<TypedPredicate>,
     /// The actions of the domain.
     pub actions: Vec<Action>,
+    /// The derived predicates of the domain (PDDL 2.2), from the `(:derived ...)` sections.
+    #[serde(default)]
+    pub derived: Vec<DerivedPredicate>,
+    /// The constraints of the domain (PDDL 3), from the optional `(:constraints ...)` section.
+    #[serde(default)]
+    pub constraints: Option<Expression>,
+    /// The keywords of the sections that were skipped because they are not modelled by the library (e.g. `:private` and `:agent` from MA-PDDL). Sections are only skipped when parsing in lenient mode, and they are not emitted by [`Domain::to_pddl`].
+    #[serde(default)]
+    pub extensions: BTreeSet<String>,
+}
+
+/// A structure definition of a domain, i.e. one of the sections that can appear in any order after the declarations.
+enum Structure {
+    Action(Action),
+    Derived(DerivedPredicate),
+}
+
+/// A section of a domain, as parsed by [`Domain::parse_recoverable`].
+enum Section {
+    Description(String),
+    Requirements(Vec<Requirement>),
+    Types(Vec<TypeDef>),
+    Constants(Vec<Constant>),
+    Predicates(Vec<TypedPredicate>),
+    Functions(Vec<TypedPredicate>),
+    Constraints(Expression),
+    Structure(Structure),
+    Extension(String),
 }
 
 impl Domain {
     /// Parse a domain from a token stream.
     pub fn parse(input: TokenStream) -> Result<Self, ParserError> {
+        Self::parse_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parse a domain from an owned string, so that the caller does not need to keep the source alive while parsing (see [`TokenStream`], which borrows its input).
+    pub fn parse_owned(source: String) -> Result<Self, ParserError> {
+        Self::parse(source.as_str().into())
+    }
+
+    /// Serialize the domain to JSON, e.g. to pass it to another process without parsing the PDDL again. See [`Domain::from_json`].
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize a domain from the JSON produced by [`Domain::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Parse a domain from a token stream, using the given options to control the parsing.
+    pub fn parse_with_options(input: TokenStream, options: &ParseOptions) -> Result<Self, ParserError> {
+        let source = input.source();
+        let input = input.with_options(options);
         let (output, domain) = delimited(
             Token::OpenParen,
             preceded(Token::Define, Domain::parse_domain),
             Token::CloseParen,
-        )(input)?;
+        )(input)
+        .map_err(|e| ParserError::from(e).with_location(source))?;
         if !output.is_empty() {
             return Err(ParserError::ExpectedEndOfInput);
         }
         Ok(domain)
     }
 
+    /// Parse a domain from a token stream, collecting the errors instead of stopping at the first one.
+    ///
+    /// When a section (e.g. an action or the predicates) fails to parse, its error is recorded and the parsing resumes at the next section, i.e. the next `(` at the top level of the domain (see [`TokenStream::resync`]). Unlike [`Domain::parse`], the sections can appear in any order.
+    ///
+    /// The returned domain is partial when there are errors: the sections that failed to parse are missing from it. `None` is only returned if the header of the domain (`(define (domain name)`) cannot be parsed.
+    pub fn parse_recoverable(input: TokenStream) -> (Option<Self>, Vec<ParserError>) {
+        log::debug!("BEGIN > parse_recoverable {:?}", input.span());
+        let source = input.source();
+        let (mut input, name) = match preceded(pair(Token::OpenParen, Token::Define), Domain::parse_name)(input) {
+            Ok(result) => result,
+            Err(e) => return (None, vec![ParserError::from(e).with_location(source)]),
+        };
+        let mut domain = Domain {
+            name,
+            description: None,
+            requirements: Vec::new(),
+            types: Vec::new(),
+            constants: Vec::new(),
+            predicates: Vec::new(),
+            functions: Vec::new(),
+            actions: Vec::new(),
+            derived: Vec::new(),
+            constraints: None,
+            extensions: BTreeSet::new(),
+        };
+        let mut errors = Vec::new();
+        loop {
+            match input.peek() {
+                None => {
+                    errors.push(
+                        ParserError::ExpectedToken(Token::CloseParen, input.span(), None).with_location(source),
+                    );
+                    break;
+                },
+                Some((Ok(Token::CloseParen), _)) => {
+                    input = input.advance();
+                    break;
+                },
+                _ => {},
+            }
+            match Domain::parse_section(input.clone()) {
+                Ok((output, section)) => {
+                    match section {
+                        Section::Description(description) => domain.description = Some(description),
+                        Section::Requirements(requirements) => {
+                            for requirement in requirements {
+                                domain.with_requirement(requirement);
+                            }
+                        },
+                        Section::Types(types) => domain.types.extend(types),
+                        Section::Constants(constants) => domain.constants.extend(constants),
+                        Section::Predicates(predicates) => domain.predicates.extend(predicates),
+                        Section::Functions(functions) => domain.functions.extend(functions),
+                        Section::Constraints(constraints) => domain.constraints = Some(constraints),
+                        Section::Structure(Structure::Action(action)) => domain.actions.push(action),
+                        Section::Structure(Structure::Derived(predicate)) => domain.derived.push(predicate),
+                        Section::Extension(keyword) => {
+                            domain.extensions.insert(keyword);
+                        },
+                    }
+                    input = output;
+                },
+                Err(e) => {
+                    errors.push(ParserError::from(e).with_location(source));
+                    input = input.resync();
+                },
+            }
+        }
+        if !input.is_empty() {
+            errors.push(ParserError::ExpectedEndOfInput);
+        }
+        log::debug!("END < parse_recoverable {:?}", input.span());
+        (Some(domain), errors)
+    }
+
+    fn parse_section(input: TokenStream) -> IResult<TokenStream, Section, ParserError> {
+        let keyword = input.peek_n(2).and_then(|tokens| tokens.into_iter().nth(1)).map(|(token, _)| token);
+        match keyword {
+            Some(Ok(Token::Description)) => map(parse_description, Section::Description)(input),
+            Some(Ok(Token::Requirements)) => map(Requirement::parse_requirements, Section::Requirements)(input),
+            Some(Ok(Token::Types)) => map(Type::parse_types, Section::Types)(input),
+            Some(Ok(Token::Constants)) => map(Constant::parse_constants, Section::Constants)(input),
+            Some(Ok(Token::Predicates)) => map(TypedPredicate::parse_predicates, Section::Predicates)(input),
+            Some(Ok(Token::Functions)) => map(TypedPredicate::parse_functions, Section::Functions)(input),
+            Some(Ok(Token::Constraints)) => map(Domain::parse_constraints, Section::Constraints)(input),
+            Some(Ok(Token::Action | Token::Operator)) => {
+                map(SimpleAction::parse, |action| Section::Structure(Structure::Action(action.into())))(input)
+            },
+            Some(Ok(Token::DurativeAction)) => {
+                map(DurativeAction::parse, |action| Section::Structure(Structure::Action(action.into())))(input)
+            },
+            Some(Ok(Token::Derived)) => map(DerivedPredicate::parse_derived, |predicate| {
+                Section::Structure(Structure::Derived(predicate))
+            })(input),
+            _ => map(Domain::parse_extension, Section::Extension)(input),
+        }
+    }
+
     fn parse_name(input: TokenStream) -> IResult<TokenStream, String, ParserError> {
         log::debug!("BEGIN > parse_name {:?}", input.span());
         let (output, name) = delimited(Token::OpenParen, preceded(Token::Domain, id), Token::CloseParen)(input)?;
@@ -56,36 +221,110 @@ impl Domain {
 
     fn parse_domain(input: TokenStream) -> IResult<TokenStream, Domain, ParserError> {
         log::debug!("BEGIN > parse_domain {:?}", input.span());
-        let (output, (name, requirements, types, constants, predicates, functions, actions)) = tuple((
-            Domain::parse_name,
-            Requirement::parse_requirements,
-            opt(Type::parse_types),
-            opt(Constant::parse_constants),
-            TypedPredicate::parse_predicates,
-            TypedPredicate::parse_functions,
-            many0(Action::parse),
+        let extensions = || many0(Domain::parse_extension);
+        let (
+            output,
+            (name, description, requirements, types, constants, predicates, functions, constraints, actions),
+        ) = tuple((
+            pair(Domain::parse_name, extensions()),
+            pair(opt(parse_description), extensions()),
+            pair(Requirement::parse_requirements, extensions()),
+            pair(opt(Type::parse_types), extensions()),
+            pair(opt(Constant::parse_constants), extensions()),
+            pair(opt(TypedPredicate::parse_predicates), extensions()),
+            pair(TypedPredicate::parse_functions, extensions()),
+            pair(opt(Domain::parse_constraints), extensions()),
+            many0(pair(
+                alt((
+                    map(Action::parse, Structure::Action),
+                    map(DerivedPredicate::parse_derived, Structure::Derived),
+                )),
+                extensions(),
+            )),
         ))(input)?;
+        let (structures, action_extensions): (Vec<_>, Vec<_>) = actions.into_iter().unzip();
+        let mut actions = Vec::new();
+        let mut derived = Vec::new();
+        for structure in structures {
+            match structure {
+                Structure::Action(action) => actions.push(action),
+                Structure::Derived(predicate) => derived.push(predicate),
+            }
+        }
+        let extensions = [
+            name.1,
+            description.1,
+            requirements.1,
+            types.1,
+            constants.1,
+            predicates.1,
+            functions.1,
+            constraints.1,
+        ]
+        .into_iter()
+        .chain(action_extensions)
+        .flatten()
+        .collect();
         let domain = Domain {
-            name,
-            requirements,
-            types: types.unwrap_or_default(),
-            constants: constants.unwrap_or_default(),
-            predicates,
-            functions,
+            name: name.0,
+            description: description.0,
+            requirements: requirements.0,
+            types: types.0.unwrap_or_default(),
+            constants: constants.0.unwrap_or_default(),
+            predicates: predicates.0.unwrap_or_default(),
+            functions: functions.0,
             actions,
+            derived,
+            constraints: constraints.0,
+            extensions,
         };
         log::debug!("END < parse_domain {:?}", output.span());
         // log::info!("Parsed domain: \n{domain:#?}");
         Ok((output, domain))
     }
 
+    fn parse_constraints(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_constraints {:?}", input.span());
+        let (output, constraints) = delimited(
+            Token::OpenParen,
+            preceded(
+                pair(Token::Constraints, requires(&[Requirement::Constraints])),
+                Expression::parse_expression,
+            ),
+            Token::CloseParen,
+        )(input)?;
+        log::debug!("END < parse_constraints {:?}", output.span());
+        Ok((output, constraints))
+    }
+
+    /// Skip a section that is not modelled by the library (`(:private ...)` or `(:agent ...)` from MA-PDDL), returning its keyword. The sections are only skipped in lenient mode.
+    fn parse_extension(input: TokenStream) -> IResult<TokenStream, String, ParserError> {
+        let (input, ()) = lenient(input)?;
+        log::debug!("BEGIN > parse_extension {:?}", input.span());
+        let (_, (_, keyword)) = peek(pair(Token::OpenParen, alt((Token::Private, Token::Agent))))(input.clone())?;
+        let keyword = keyword.to_ascii_lowercase();
+        let (output, _) = skip_balanced(input)?;
+        log::debug!("END < parse_extension {:?}", output.span());
+        Ok((output, keyword))
+    }
+
     /// Convert the domain to PDDL.
     pub fn to_pddl(&self) -> String {
+        self.to_pddl_with_options(&PrettyOptions::default())
+    }
+
+    /// Convert the domain to PDDL, using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
         let mut output = String::new();
 
         // Name
         output.push_str(&format!("(define (domain {})\n", self.name));
 
+        // Description
+        if let Some(description) = &self.description {
+            output.push_str(&format!("(:description {})\n", escape(description)));
+        }
+
         // Requirements
         if !self.requirements.is_empty() {
             output.push_str(&format!(
@@ -112,10 +351,14 @@ impl Domain {
 
         // Constants
         if !self.constants.is_empty() {
+            let mut constants = self.constants.iter().collect::<Vec<_>>();
+            if options.sort_constants {
+                constants.sort_by(|a, b| (&a.type_, &a.name).cmp(&(&b.type_, &b.name)));
+            }
             output.push_str(&format!(
                 "(:constants \n{}\n)\n",
-                self.constants
-                    .iter()
+                constants
+                    .into_iter()
                     .map(Constant::to_pddl)
                     .collect::<Vec<String>>()
                     .join("\n")
@@ -134,25 +377,49 @@ impl Domain {
             ));
         }
 
-        // Functions
+        // Functions (consecutive functions with the same return type are grouped)
         if !self.functions.is_empty() {
+            let mut groups: Vec<Vec<&TypedPredicate>> = Vec::new();
+            for function in &self.functions {
+                match groups.last_mut() {
+                    Some(group) if group[0].return_type == function.return_type => group.push(function),
+                    _ => groups.push(vec![function]),
+                }
+            }
             output.push_str(&format!(
                 "(:functions \n{}\n)\n",
-                self.functions
-                    .iter()
-                    .map(TypedPredicate::to_pddl)
+                groups
+                    .into_iter()
+                    .map(|group| {
+                        let functions = group.iter().map(|f| f.to_pddl()).collect::<Vec<String>>().join("\n");
+                        match &group[0].return_type {
+                            Some(return_type) => format!("{functions} - {}", return_type.to_pddl()),
+                            None => functions,
+                        }
+                    })
                     .collect::<Vec<String>>()
                     .join("\n")
             ));
         }
 
+        // Constraints
+        if let Some(constraints) = &self.constraints {
+            output.push_str(&format!("(:constraints\n{}\n)\n", options.expression(constraints)));
+        }
+
+        // Derived predicates
+        for derived in &self.derived {
+            output.push_str(&derived.to_pddl_with_options(options));
+            output.push('\n');
+        }
+
         // Actions
         if !self.actions.is_empty() {
             output.push_str(
                 &self
                     .actions
                     .iter()
-                    .map(Action::to_pddl)
+                    .map(|action| action.to_pddl_with_options(options))
                     .collect::<Vec<String>>()
                     .join("\n\n"),
             );
@@ -163,4 +430,789 @@ impl Domain {
 
         output
     }
+
+    /// Add a requirement to the domain, unless it is already declared.
+    pub fn with_requirement(&mut self, requirement: Requirement) {
+        if !self.requirements.contains(&requirement) {
+            self.requirements.push(requirement);
+        }
+    }
+
+    /// Infer the requirements that the domain actually uses, by scanning its types, functions and actions. This can be compared with [`Domain::requirements`] to keep the `:requirements` section accurate.
+    ///
+    /// The following requirements are inferred:
+    ///
+    /// - `Strips` if the domain has actions, and `Typing` if it declares or uses types.
+    /// - `DurativeActions` if the domain has durative actions, and `DurativeInequalities` if one of their durations is not a single `(= ?duration ...)` constraint.
+    /// - `DerivedPredicates` if the domain has derived predicates, and `Constraints` if it has constraints.
+    /// - `NumericFluents` if the domain declares functions, compares or computes numeric values, or updates fluents (e.g. with `increase`).
+    /// - `NegativePreconditions`, `DisjunctivePreconditions`, `UniversalPreconditions`, `ExistentialPreconditions` and `Equality` if the preconditions use `not`, `or`, `forall`, `exists` or `=` between objects.
+    /// - `ConditionalEffects` if the effects use `forall` or `when`, and `Preferences` if the preconditions use `preference`.
+    ///
+    /// The requirements are returned sorted and without duplicates.
+    pub fn infer_requirements(&self) -> Vec<Requirement> {
+        let mut requirements = BTreeSet::new();
+        if !self.actions.is_empty() {
+            requirements.insert(Requirement::Strips);
+        }
+        if !self.types.is_empty() || self.type_usage().keys().any(|name| name != "object") {
+            requirements.insert(Requirement::Typing);
+        }
+        if !self.functions.is_empty() {
+            requirements.insert(Requirement::NumericFluents);
+        }
+        if !self.derived.is_empty() {
+            requirements.insert(Requirement::DerivedPredicates);
+        }
+        if self.constraints.is_some() {
+            requirements.insert(Requirement::Constraints);
+        }
+        let is_object = |expression: &Expression| Self::is_object_term(expression, |name| self.is_constant(name));
+        for action in &self.actions {
+            if let Action::Durative(action) = action {
+                requirements.insert(Requirement::DurativeActions);
+                if !matches!(action.duration, Expression::BinaryOp(BinaryOp::Equal, _, _)) {
+                    requirements.insert(Requirement::DurativeInequalities);
+                }
+            }
+            let precondition = action.precondition();
+            let mut stack = precondition.iter().collect::<Vec<_>>();
+            while let Some(expression) = stack.pop() {
+                match expression {
+                    Expression::Not(_) => {
+                        requirements.insert(Requirement::NegativePreconditions);
+                    },
+                    Expression::Or(_) => {
+                        requirements.insert(Requirement::DisjunctivePreconditions);
+                    },
+                    Expression::Forall(_, _) => {
+                        requirements.insert(Requirement::UniversalPreconditions);
+                    },
+                    Expression::Exists(_, _) => {
+                        requirements.insert(Requirement::ExistentialPreconditions);
+                    },
+                    Expression::Preference(_, _) => {
+                        requirements.insert(Requirement::Preferences);
+                    },
+                    Expression::BinaryOp(BinaryOp::Equal, lhs, rhs) if is_object(lhs) && is_object(rhs) => {
+                        requirements.insert(Requirement::Equality);
+                    },
+                    Expression::BinaryOp(_, _, _) => {
+                        requirements.insert(Requirement::NumericFluents);
+                    },
+                    _ => {},
+                }
+                stack.extend(expression.children());
+            }
+            let effect = action.effect();
+            let mut stack = vec![&effect];
+            while let Some(expression) = stack.pop() {
+                match expression {
+                    Expression::Forall(_, _) | Expression::When(_, _) => {
+                        requirements.insert(Requirement::ConditionalEffects);
+                    },
+                    Expression::Assign(_, _)
+                    | Expression::Increase(_, _)
+                    | Expression::Decrease(_, _)
+                    | Expression::ScaleUp(_, _)
+                    | Expression::ScaleDown(_, _) => {
+                        requirements.insert(Requirement::NumericFluents);
+                    },
+                    _ => {},
+                }
+                stack.extend(expression.children());
+            }
+        }
+        requirements.into_iter().collect()
+    }
+
+    /// Walk the domain with the given visitor.
+    ///
+    /// The types, predicates, functions and actions are visited in that order. For each action, [`DomainVisitor::visit_action`] is called first, then [`DomainVisitor::visit_expression`] is called for every (sub-)expression of its duration, precondition and effect, in pre-order.
+    pub fn accept(&self, visitor: &mut impl DomainVisitor) {
+        self.types.iter().for_each(|type_def| visitor.visit_type(type_def));
+        self.predicates.iter().for_each(|predicate| visitor.visit_predicate(predicate));
+        self.functions.iter().for_each(|function| visitor.visit_function(function));
+        for action in &self.actions {
+            visitor.visit_action(action);
+            if let Action::Durative(action) = action {
+                Self::accept_expression(&action.duration, visitor);
+            }
+            if let Some(precondition) = action.precondition() {
+                Self::accept_expression(&precondition, visitor);
+            }
+            Self::accept_expression(&action.effect(), visitor);
+        }
+    }
+
+    fn accept_expression(expression: &Expression, visitor: &mut impl DomainVisitor) {
+        visitor.visit_expression(expression);
+        for child in expression.children() {
+            Self::accept_expression(child, visitor);
+        }
+    }
+
+    /// Check that the domain is consistent with its own declarations, reporting every violation as a [`ValidationError`]:
+    ///
+    /// - every type that is used is declared (see [`Domain::validate_types`]),
+    /// - every atom of the actions refers to a declared predicate or function, with the right number of arguments, and arguments of the right types (see [`Domain::validate_predicates`]).
+    ///
+    /// Unlike parsing, which only checks the syntax, this is a semantic check: a domain that fails it still parses.
+    ///
+    /// # Errors
+    ///
+    /// Returns all of the violations, types first.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let errors = [self.type_errors(), self.predicate_errors()].concat();
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Check that the domain is well formed, combining all of the validators:
+    ///
+    /// - [`Domain::validate_types`]: every type that is used is declared.
+    /// - [`Domain::validate_predicates`]: every atom of the actions refers to a declared predicate or function, with the right number of arguments.
+    /// - [`Domain::validate_effects`]: no action effect both adds and deletes the same literal.
+    /// - [`Domain::validate_durations`]: the duration of every durative action constrains `?duration`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the messages of all of the validators that failed.
+    pub fn is_well_typed(&self) -> Result<(), Vec<String>> {
+        let errors = [
+            self.validate_types(),
+            self.validate_predicates(),
+            self.validate_effects(),
+            self.validate_durations(),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .flatten()
+        .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Check that every type used by the domain (as a parent type, or as the type of a constant or of a parameter of a predicate, function or action) is declared in `:types`. The `object` type is always declared.
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per undeclared type usage.
+    pub fn validate_types(&self) -> Result<(), Vec<String>> {
+        Self::into_messages(self.type_errors())
+    }
+
+    fn type_errors(&self) -> Vec<ValidationError> {
+        let declared = |name: &str| name == "object" || self.types.iter().any(|t| t.name == name);
+        let mut errors = Vec::new();
+        let mut check = |type_: &Type, context: String| {
+            for name in type_.names().iter().filter(|name| !declared(name)) {
+                errors.push(ValidationError::UndeclaredType {
+                    name: name.clone(),
+                    context: context.clone(),
+                });
+            }
+        };
+        for type_def in &self.types {
+            if let Some(parent) = &type_def.parent {
+                check(&parent.as_str().into(), format!("type `{}`", type_def.name));
+            }
+        }
+        for constant in &self.constants {
+            check(&constant.type_, format!("constant `{}`", constant.name));
+        }
+        for predicate in &self.predicates {
+            for parameter in &predicate.parameters {
+                check(&parameter.type_, format!("predicate `{}`", predicate.name));
+            }
+        }
+        for function in &self.functions {
+            for parameter in &function.parameters {
+                check(&parameter.type_, format!("function `{}`", function.name));
+            }
+        }
+        for action in &self.actions {
+            for parameter in action.parameters() {
+                check(&parameter.type_, format!("action `{}`", action.name()));
+            }
+        }
+        errors
+    }
+
+    /// Check that every atom used in the preconditions and effects of the actions refers to a declared predicate or function, with the declared number of arguments. When the type of an argument is known (a parameter of the action, a quantified variable or a constant), it must also be a subtype of the declared type of the parameter (see [`Domain::is_subtype`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per undeclared or misused atom.
+    pub fn validate_predicates(&self) -> Result<(), Vec<String>> {
+        Self::into_messages(self.predicate_errors())
+    }
+
+    fn predicate_errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for action in &self.actions {
+            let mut bindings = action
+                .parameters()
+                .iter()
+                .map(|parameter| (parameter.name.as_str(), &parameter.type_))
+                .collect::<Vec<_>>();
+            let precondition = action.precondition();
+            let effect = action.effect();
+            for expression in precondition.iter().chain(Some(&effect)) {
+                self.atom_errors(action.name(), expression, &mut bindings, &mut errors);
+            }
+        }
+        errors
+    }
+
+    /// Check the atoms of an expression against their declarations, in pre-order. `bindings` holds the types of the variables in scope, the innermost last.
+    fn atom_errors<'a>(
+        &self,
+        action: &str,
+        expression: &'a Expression,
+        bindings: &mut Vec<(&'a str, &'a Type)>,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        match expression {
+            Expression::Atom { name, parameters } => {
+                let declaration = self
+                    .predicates
+                    .iter()
+                    .chain(&self.functions)
+                    .find(|predicate| predicate.name == *name);
+                match declaration {
+                    None => errors.push(ValidationError::UndeclaredPredicate {
+                        action: action.to_string(),
+                        predicate: name.clone(),
+                    }),
+                    Some(declaration) if declaration.parameters.len() != parameters.len() => {
+                        errors.push(ValidationError::ArityMismatch {
+                            action: action.to_string(),
+                            predicate: name.clone(),
+                            expected: declaration.parameters.len(),
+                            found: parameters.len(),
+                        });
+                    },
+                    Some(declaration) => {
+                        for (argument, parameter) in parameters.iter().zip(&declaration.parameters) {
+                            let type_ = if argument.is_variable() {
+                                bindings
+                                    .iter()
+                                    .rev()
+                                    .find(|(variable, _)| *variable == argument.as_str())
+                                    .map(|(_, type_)| *type_)
+                            }
+                            else {
+                                self.constants.iter().find(|c| c.name == argument.as_str()).map(|c| &c.type_)
+                            };
+                            match type_ {
+                                Some(type_) if !self.is_type_compatible(type_, &parameter.type_) => {
+                                    errors.push(ValidationError::TypeMismatch {
+                                        action: action.to_string(),
+                                        predicate: name.clone(),
+                                        argument: argument.to_string(),
+                                        expected: parameter.type_.clone(),
+                                        found: type_.clone(),
+                                    });
+                                },
+                                _ => {},
+                            }
+                        }
+                    },
+                }
+            },
+            Expression::Forall(variables, body) | Expression::Exists(variables, body) => {
+                let scope = bindings.len();
+                bindings.extend(variables.iter().map(|variable| (variable.name.as_str(), &variable.type_)));
+                self.atom_errors(action, body, bindings, errors);
+                bindings.truncate(scope);
+            },
+            _ => {
+                for child in expression.children() {
+                    self.atom_errors(action, child, bindings, errors);
+                }
+            },
+        }
+    }
+
+    fn into_messages(errors: Vec<ValidationError>) -> Result<(), Vec<String>> {
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors.iter().map(ValidationError::to_string).collect())
+        }
+    }
+
+    /// Check that the duration of every durative action is a comparison (or a conjunction of comparisons) on `?duration`, such as `(= ?duration 10)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per durative action with an invalid duration.
+    pub fn validate_durations(&self) -> Result<(), Vec<String>> {
+        fn constrains_duration(expression: &Expression) -> bool {
+            match expression {
+                Expression::And(expressions) => {
+                    !expressions.is_empty() && expressions.iter().all(constrains_duration)
+                },
+                Expression::BinaryOp(op, lhs, _) => {
+                    op.is_comparison() && matches!(&**lhs, Expression::Term(term) if term.as_str() == "?duration")
+                },
+                _ => false,
+            }
+        }
+
+        let errors = self
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Durative(action) if !constrains_duration(&action.duration) => Some(format!(
+                    "Durative action `{}` has an invalid duration {}",
+                    action.name,
+                    action.duration.to_pddl()
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Check that no action effect both adds and deletes the same literal.
+    ///
+    /// For each action, the effect is flattened into literals and any atom that appears both positively and negatively with the same arguments (at the same time point, for durative actions) is reported. Quantified effects are analyzed on a best-effort basis, without instantiating the quantified variables. Conditional effects are ignored, since their conditions may be mutually exclusive.
+    ///
+    /// # Errors
+    ///
+    /// Returns the list of inconsistencies found, one message per conflicting literal.
+    pub fn validate_effects(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for action in &self.actions {
+            let effect = action.effect();
+            let mut literals = Vec::new();
+            Self::effect_literals(&effect, None, true, &mut literals);
+            for (i, (instant, positive, atom)) in literals.iter().enumerate() {
+                let conflict = literals[..i].iter().any(|(other_instant, other_positive, other_atom)| {
+                    other_instant == instant && other_positive != positive && other_atom == atom
+                });
+                if conflict {
+                    errors.push(format!(
+                        "Action `{}` both adds and deletes {}",
+                        action.name(),
+                        atom.to_pddl()
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Find the equalities between objects (e.g. `(= ?x ?y)`) used by the domain when it does not declare the `:equality` requirement (nor a requirement that implies it, like `:adl`). The domain still parses, since most planners accept them, so each use is reported as a warning.
+    ///
+    /// The actions (preconditions and effects), the derived predicates and the constraints are checked. Equalities between numeric expressions are comparisons, so they do not need the requirement.
+    pub fn uses_equality_without_requirement(&self) -> Vec<String> {
+        if self.declares_equality() {
+            return Vec::new();
+        }
+        let is_constant = |name: &str| self.is_constant(name);
+        let mut warnings = Vec::new();
+        for action in &self.actions {
+            for expression in action.precondition().iter().chain(Some(&action.effect())) {
+                for equality in Self::object_equalities(expression, is_constant) {
+                    warnings.push(format!(
+                        "Action `{}` uses `{}` without the `:equality` requirement",
+                        action.name(),
+                        equality.to_pddl()
+                    ));
+                }
+            }
+        }
+        for derived in &self.derived {
+            for equality in Self::object_equalities(&derived.body, is_constant) {
+                warnings.push(format!(
+                    "Derived predicate `{}` uses `{}` without the `:equality` requirement",
+                    derived.predicate.name,
+                    equality.to_pddl()
+                ));
+            }
+        }
+        for equality in self.constraints.iter().flat_map(|c| Self::object_equalities(c, is_constant)) {
+            warnings.push(format!("Constraints use `{}` without the `:equality` requirement", equality.to_pddl()));
+        }
+        warnings
+    }
+
+    /// Check that the domain declares the requirements of the numeric and temporal features it uses. The domain still parses without them, so each inconsistency is reported as a warning:
+    ///
+    /// - Functions need `:numeric-fluents` (or `:fluents`). `:action-costs` is also enough to declare functions such as `(total-cost)`.
+    /// - Numeric comparisons (e.g. `(> (fuel ?t) 0)`) in the actions need `:numeric-fluents` (or `:fluents`).
+    /// - Durative actions need `:durative-actions`.
+    pub fn validate_requirement_consistency(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let numeric = self.missing_requirements(&[Requirement::NumericFluents]).is_empty();
+        let durative = self.missing_requirements(&[Requirement::DurativeActions]).is_empty();
+        if !numeric && !self.missing_requirements(&[Requirement::ActionCosts]).is_empty() {
+            for function in &self.functions {
+                warnings.push(format!(
+                    "Function `{}` is declared without the `:numeric-fluents` requirement",
+                    function.name
+                ));
+            }
+        }
+        let is_object = |expression: &Expression| Self::is_object_term(expression, |name| self.is_constant(name));
+        for action in &self.actions {
+            if !numeric {
+                for expression in action.precondition().iter().chain(Some(&action.effect())) {
+                    expression.visit(&mut |expression| match expression {
+                        Expression::BinaryOp(BinaryOp::Equal, lhs, rhs) if is_object(lhs) && is_object(rhs) => {},
+                        Expression::BinaryOp(op, _, _) if op.is_comparison() => warnings.push(format!(
+                            "Action `{}` uses `{}` without the `:numeric-fluents` requirement",
+                            action.name(),
+                            expression.to_pddl()
+                        )),
+                        _ => {},
+                    });
+                }
+            }
+            if !durative && matches!(action, Action::Durative(_)) {
+                warnings.push(format!(
+                    "Durative action `{}` is defined without the `:durative-actions` requirement",
+                    action.name()
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Returns `true` if the domain declares the `:equality` requirement, directly or through a requirement that implies it (e.g. `:adl`).
+    pub(crate) fn declares_equality(&self) -> bool {
+        self.missing_requirements(&[Requirement::Equality]).is_empty()
+    }
+
+    /// Returns the requirements of `needed` that the domain does not declare, in order and without duplicates. A requirement implied by a declared one (see [`Requirement::implied`]) is not missing, e.g. `:adl` provides `:conditional-effects`.
+    ///
+    /// This is useful to check a domain against the capabilities of a planner before handing it over.
+    pub fn missing_requirements(&self, needed: &[Requirement]) -> Vec<Requirement> {
+        let declared = self.requirements.iter().flat_map(Requirement::implied).collect::<BTreeSet<_>>();
+        let mut missing = Vec::new();
+        for requirement in needed {
+            if !declared.contains(requirement) && !missing.contains(requirement) {
+                missing.push(requirement.clone());
+            }
+        }
+        missing
+    }
+
+    fn is_constant(&self, name: &str) -> bool {
+        self.constants.iter().any(|c| c.name == name)
+    }
+
+    /// Returns `true` if the expression is a term that denotes an object, i.e. a variable or an object name.
+    fn is_object_term(expression: &Expression, is_object: impl Fn(&str) -> bool) -> bool {
+        match expression {
+            Expression::Term(_) => true,
+            Expression::Atom { name, parameters } => parameters.is_empty() && is_object(name),
+            _ => false,
+        }
+    }
+
+    /// Collect the `=` comparisons between two objects of the expression, in the order they appear.
+    pub(crate) fn object_equalities(expression: &Expression, is_object: impl Fn(&str) -> bool) -> Vec<&Expression> {
+        let mut equalities = Vec::new();
+        expression.visit(&mut |expression| {
+            if let Expression::BinaryOp(BinaryOp::Equal, lhs, rhs) = expression {
+                if Self::is_object_term(lhs, &is_object) && Self::is_object_term(rhs, &is_object) {
+                    equalities.push(expression);
+                }
+            }
+        });
+        equalities
+    }
+
+    /// Find the action with the given name. Names are matched case-insensitively, like PDDL identifiers.
+    pub fn get_action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Append a copy of the action `name`, renamed to `new_name`, e.g. to create a variant of an action that is then modified through [`Domain::actions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParserError::UnknownAction`] if the domain does not declare `name`, and [`ParserError::DuplicateAction`] if it already declares `new_name`. In both cases, the domain is left unchanged.
+    pub fn duplicate_action(&mut self, name: &str, new_name: &str) -> Result<(), ParserError> {
+        let mut action = self
+            .get_action(name)
+            .cloned()
+            .ok_or_else(|| ParserError::UnknownAction(name.to_string()))?;
+        if self.get_action(new_name).is_some() {
+            return Err(ParserError::DuplicateAction(new_name.to_string()));
+        }
+        match &mut action {
+            Action::Simple(action) => action.name = new_name.to_string(),
+            Action::Durative(action) => action.name = new_name.to_string(),
+        }
+        self.actions.push(action);
+        Ok(())
+    }
+
+    /// Find the predicate with the given name. Names are matched case-insensitively, like PDDL identifiers.
+    pub fn get_predicate(&self, name: &str) -> Option<&TypedPredicate> {
+        self.predicates.iter().find(|predicate| predicate.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find the function with the given name. Names are matched case-insensitively, like PDDL identifiers.
+    pub fn get_function(&self, name: &str) -> Option<&TypedPredicate> {
+        self.functions.iter().find(|function| function.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find the actions that add the given predicate, i.e. whose effect contains a positive literal of the predicate, possibly inside a conditional effect.
+    pub fn producers_of(&self, predicate: &str) -> Vec<&Action> {
+        self.actions
+            .iter()
+            .filter(|action| {
+                let effect = action.effect();
+                let mut effects = vec![&effect];
+                let mut stack = vec![&effect];
+                while let Some(expression) = stack.pop() {
+                    if let Expression::When(_, effect) = expression {
+                        effects.push(effect);
+                    }
+                    stack.extend(expression.children());
+                }
+                effects.into_iter().any(|effect| Self::has_positive_literal(effect, predicate))
+            })
+            .collect()
+    }
+
+    /// Find the actions that may require the given predicate, i.e. whose precondition contains a positive literal of the predicate anywhere, including inside disjunctions, quantifiers and temporal conditions.
+    pub fn consumers_of(&self, predicate: &str) -> Vec<&Action> {
+        self.actions
+            .iter()
+            .filter(|action| {
+                action
+                    .precondition()
+                    .map_or(false, |precondition| Self::has_positive_condition(&precondition, predicate, true))
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the condition contains a literal of the predicate that is positive, given the polarity of the condition itself. Comparisons and numeric expressions are skipped, as their atoms are function applications.
+    fn has_positive_condition(condition: &Expression, predicate: &str, positive: bool) -> bool {
+        match condition {
+            Expression::Atom { name, .. } => positive && name == predicate,
+            Expression::Not(condition) => Self::has_positive_condition(condition, predicate, !positive),
+            Expression::BinaryOp(..) => false,
+            _ => condition
+                .children()
+                .into_iter()
+                .any(|condition| Self::has_positive_condition(condition, predicate, positive)),
+        }
+    }
+
+    fn has_positive_literal(expression: &Expression, predicate: &str) -> bool {
+        let mut literals = Vec::new();
+        Self::effect_literals(expression, None, true, &mut literals);
+        literals
+            .iter()
+            .any(|(_, positive, atom)| *positive && matches!(atom, Expression::Atom { name, .. } if name == predicate))
+    }
+
+    fn effect_literals<'a>(
+        effect: &'a Expression,
+        instant: Option<&'a DurationInstant>,
+        positive: bool,
+        literals: &mut Vec<(Option<&'a DurationInstant>, bool, &'a Expression)>,
+    ) {
+        match effect {
+            Expression::Atom { .. } => literals.push((instant, positive, effect)),
+            Expression::And(expressions) => {
+                for expression in expressions {
+                    Self::effect_literals(expression, instant, positive, literals);
+                }
+            },
+            Expression::Not(expression) => Self::effect_literals(expression, instant, !positive, literals),
+            Expression::Forall(_, expression) => Self::effect_literals(expression, instant, positive, literals),
+            Expression::Duration(instant, expression) => {
+                Self::effect_literals(expression, Some(instant), positive, literals);
+            },
+            _ => {},
+        }
+    }
+
+    /// Enumerate every ground atom of the declared predicates, using the objects of the problem and the constants of the domain as arguments. Each argument must be compatible with the type of the corresponding parameter of the predicate.
+    ///
+    /// The atoms are returned grouped by predicate, in declaration order. The number of atoms of a predicate is the product of the number of candidates for each of its parameters, so it grows exponentially with the arity of the predicates and can be very large for big problems.
+    pub fn ground_predicate_instances(&self, problem: &Problem) -> Vec<Expression> {
+        let candidates = problem
+            .objects
+            .iter()
+            .map(|object| (&object.name, &object.type_))
+            .chain(self.constants.iter().map(|constant| (&constant.name, &constant.type_)))
+            .collect::<Vec<_>>();
+        let mut atoms = Vec::new();
+        for predicate in &self.predicates {
+            let mut arguments = vec![Vec::new()];
+            for parameter in &predicate.parameters {
+                let objects = candidates
+                    .iter()
+                    .filter(|(_, type_)| self.is_type_compatible(type_, &parameter.type_))
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>();
+                arguments = arguments
+                    .into_iter()
+                    .flat_map(|prefix| {
+                        objects.iter().map(move |object| {
+                            let mut arguments = prefix.clone();
+                            arguments.push(Parameter::from((*object).clone()));
+                            arguments
+                        })
+                    })
+                    .collect();
+            }
+            atoms.extend(arguments.into_iter().map(|parameters| Expression::Atom {
+                name: predicate.name.clone(),
+                parameters,
+            }));
+        }
+        atoms
+    }
+
+    /// Describe the typed signature of each predicate, in declaration order. This is a flattened projection of [`Domain::predicates`] (without the comments), meant to be serialized for tools such as editors or validators.
+    pub fn predicate_schema(&self) -> Vec<PredicateSchema> {
+        self.predicates.iter().map(PredicateSchema::from).collect()
+    }
+
+    /// Count how many times each type is used by the parameters of predicates, functions and actions, and by constants. Each member of an `either` type is counted separately.
+    pub fn type_usage(&self) -> HashMap<String, usize> {
+        let mut usage = HashMap::new();
+        let types = self
+            .predicates
+            .iter()
+            .chain(&self.functions)
+            .flat_map(|predicate| &predicate.parameters)
+            .chain(self.actions.iter().flat_map(Action::parameters))
+            .map(|parameter| &parameter.type_)
+            .chain(self.constants.iter().map(|constant| &constant.type_));
+        for type_ in types {
+            for name in type_.names() {
+                *usage.entry(name.clone()).or_insert(0) += 1;
+            }
+        }
+        usage
+    }
+
+    /// Summarize the effects of each action, by action name. See [`Action::effect_summary`].
+    pub fn action_effect_balance(&self) -> HashMap<String, EffectSummary> {
+        self.actions
+            .iter()
+            .map(|action| (action.name().to_string(), action.effect_summary()))
+            .collect()
+    }
+
+    /// The largest number of parameters of an action, or `0` if the domain has no actions.
+    pub fn max_action_arity(&self) -> usize {
+        self.actions.iter().map(|action| action.parameters().len()).max().unwrap_or(0)
+    }
+
+    /// The largest number of parameters of a predicate, or `0` if the domain has no predicates. Derived predicates are included, functions are not.
+    pub fn max_predicate_arity(&self) -> usize {
+        self.predicates
+            .iter()
+            .chain(self.derived.iter().map(|derived| &derived.predicate))
+            .map(|predicate| predicate.parameters.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The largest number of parameters of any declaration: an action, a predicate (derived or not) or a function. This bounds the number of arguments of every atom and action of the domain.
+    pub fn max_parameters_any(&self) -> usize {
+        let max_function_arity = self.functions.iter().map(|f| f.parameters.len()).max().unwrap_or(0);
+        self.max_action_arity().max(self.max_predicate_arity()).max(max_function_arity)
+    }
+
+    /// Sort the actions by name. The sort is stable, so actions with the same name keep their relative order.
+    pub fn sort_actions_by_name(&mut self) {
+        self.actions.sort_by(|a, b| a.name().cmp(b.name()));
+    }
+
+    /// Sort the predicates by name. The sort is stable, so predicates with the same name keep their relative order.
+    pub fn sort_predicates_by_name(&mut self) {
+        self.predicates.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Sort the types so that every type is declared after its parent, and by name otherwise. Types whose parent is not declared (e.g. `object`) come first. If the hierarchy contains a cycle, the types of the cycle are kept in name order.
+    pub fn sort_types_topologically(&mut self) {
+        let mut remaining = std::mem::take(&mut self.types);
+        remaining.sort_by(|a, b| a.name.cmp(&b.name));
+        while !remaining.is_empty() {
+            let next = remaining
+                .iter()
+                .position(|type_def| {
+                    type_def
+                        .parent
+                        .as_ref()
+                        .map_or(true, |parent| !remaining.iter().any(|other| &other.name == parent))
+                })
+                .unwrap_or(0);
+            self.types.push(remaining.remove(next));
+        }
+    }
+
+    /// Returns `true` if `child` is `ancestor` or one of its (transitive) subtypes. Every type is a subtype of `object`.
+    pub fn is_subtype(&self, child: &str, ancestor: &str) -> bool {
+        ancestor == "object" || child == ancestor || self.ancestors(child).iter().any(|name| name == ancestor)
+    }
+
+    /// Returns the ancestors of a type, from its parent up to `object`, by walking the `parent` chain of the type definitions. A type without a parent (or that is not declared) is a subtype of `object`. The walk stops if the hierarchy contains a cycle, so every ancestor is only returned once.
+    pub fn ancestors(&self, type_name: &str) -> Vec<String> {
+        let mut ancestors = Vec::new();
+        let mut current = type_name.to_string();
+        while current != "object" {
+            let parent = self
+                .types
+                .iter()
+                .find(|t| t.name == current)
+                .and_then(|t| t.parent.clone())
+                .unwrap_or_else(|| "object".to_string());
+            if parent == type_name || ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent.clone());
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// Returns `true` if a value of type `type_` can be used where a value of type `expected` is required.
+    pub(crate) fn is_type_compatible(&self, type_: &Type, expected: &Type) -> bool {
+        let expected = expected.names();
+        let accepts = |name: &String| expected.iter().any(|expected| self.is_subtype(name, expected));
+        match type_ {
+            Type::Simple(name) => accepts(name),
+            Type::Either(names) => names.iter().all(accepts),
+        }
+    }
+}
+
+impl std::fmt::Display for Domain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pddl())
+    }
 }
@@ -1,16 +1,22 @@
+use std::ops::Range;
+
 use nom::combinator::{map, opt};
-use nom::sequence::{delimited, preceded, tuple};
+use nom::sequence::{delimited, pair, preceded, tuple};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
 use super::expression::Expression;
+use super::requirement::Requirement;
 use super::typed_parameter::TypedParameter;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::pretty::PrettyOptions;
+use crate::tokens::{id, requires};
 
 /// An action with typed parameters.
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// [`PartialEq`], [`Ord`] and [`Hash`](std::hash::Hash) are implemented manually to ignore the [`DurativeAction::span`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DurativeAction {
     /// The name of the action.
     pub name: String,
@@ -21,20 +27,35 @@ pub struct DurativeAction {
     pub duration: Expression,
     /// The condition of the action.
     pub condition: Option<Expression>,
-    /// The effect of the action.
+    /// The effect of the action. An action without `:effect` has an empty effect, i.e. `(and)`.
     pub effect: Expression,
+    /// The comment documenting the action, i.e. the comment lines immediately preceding its definition. It is only captured when parsing with [`crate::options::ParseOptions::preserve_comments`].
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// The source range of the action, from its opening to its closing parenthesis. It is only recorded when parsing with [`crate::options::ParseOptions::record_spans`], and it is ignored by comparisons and hashing.
+    #[serde(skip)]
+    pub span: Option<Range<usize>>,
 }
 
+impl_cmp_by_fields!(DurativeAction { name, parameters, duration, condition, effect, doc });
+
 impl DurativeAction {
     /// Parse a list of actions from a token stream.
     pub fn parse(input: TokenStream) -> IResult<TokenStream, DurativeAction, ParserError> {
         log::debug!("BEGIN > parse_durative_action {:?}", input.span());
         log::debug!("Parsing action: {:?}", input.peek_n(10));
-        let (output, action) = map(
+        let doc = if input.options().preserve_comments {
+            input.leading_comment()
+        }
+        else {
+            None
+        };
+        let start = input.clone();
+        let (output, mut action) = map(
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::DurativeAction,
+                    pair(Token::DurativeAction, requires(&[Requirement::DurativeActions])),
                     tuple((
                         id,
                         preceded(
@@ -47,7 +68,7 @@ impl DurativeAction {
                         ),
                         preceded(Token::Duration, Expression::parse_expression),
                         opt(preceded(Token::Condition, Expression::parse_expression)),
-                        preceded(Token::Effect, Expression::parse_expression),
+                        opt(preceded(Token::Effect, Expression::parse_expression)),
                     )),
                 ),
                 Token::CloseParen,
@@ -57,16 +78,31 @@ impl DurativeAction {
                 parameters,
                 duration,
                 condition,
-                effect,
+                effect: effect.unwrap_or_else(|| Expression::And(vec![])),
+                doc: doc.clone(),
+                span: None,
             },
         )(input)?;
+        if output.options().record_spans {
+            action.span = Some(output.span_since(&start));
+        }
         log::debug!("END < parse_action {:?}", output.span());
         Ok((output, action))
     }
 
-    /// Convert the action to PDDL.
+    /// Convert the action to PDDL. The documentation comment, if any, is emitted on the lines preceding the definition.
     pub fn to_pddl(&self) -> String {
-        let mut pddl = String::new();
+        self.to_pddl_with_options(&PrettyOptions::default())
+    }
+
+    /// Convert the action to PDDL, using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
+        let mut pddl = self
+            .doc
+            .iter()
+            .flat_map(|doc| doc.lines())
+            .map(|line| format!("; {line}\n"))
+            .collect::<String>();
 
         // Action name
         pddl.push_str(&format!("(:durative-action {}\n", self.name));
@@ -82,17 +118,23 @@ impl DurativeAction {
         ));
 
         // Duration
-        pddl.push_str(&format!(":duration {}\n", self.duration.to_pddl()));
+        pddl.push_str(&format!(":duration {}\n", options.expression(&self.duration)));
 
         // Condition
         if let Some(condition) = &self.condition {
-            pddl.push_str(&format!(":condition {}\n", condition.to_pddl()));
+            pddl.push_str(&format!(":condition {}\n", options.expression(condition)));
         }
 
         // Effect
-        pddl.push_str(&format!(":effect \n{}\n", self.effect.to_pddl()));
+        pddl.push_str(&format!(":effect \n{}\n", options.expression(&self.effect)));
 
         pddl.push(')');
         pddl
     }
 }
+
+impl std::fmt::Display for DurativeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pddl())
+    }
+}
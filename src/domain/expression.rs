@@ -1,15 +1,19 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
 use nom::branch::alt;
-use nom::combinator::map;
+use nom::combinator::{map, opt};
 use nom::multi::many0;
 use nom::sequence::{delimited, pair, preceded, tuple};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
 use super::parameter::Parameter;
+use super::requirement::Requirement;
 use crate::domain::typed_parameter::TypedParameter;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::{id, integer, var};
+use crate::tokens::{float, id, integer, number, predicate_name, requires, var};
 
 /// An enumeration of binary operations that can be used in expressions.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -24,6 +28,43 @@ pub enum BinaryOp {
     Divide,
     /// Equality operation.
     Equal,
+    /// Less than comparison.
+    LessThan,
+    /// Greater than comparison.
+    GreaterThan,
+    /// Less than or equal comparison.
+    LessThanOrEqual,
+    /// Greater than or equal comparison.
+    GreaterThanOrEqual,
+}
+
+impl BinaryOp {
+    /// Returns `true` if the operation is a comparison (`=`, `<`, `>`, `<=` or `>=`) rather than an arithmetic operation.
+    pub const fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Equal
+                | BinaryOp::LessThan
+                | BinaryOp::GreaterThan
+                | BinaryOp::LessThanOrEqual
+                | BinaryOp::GreaterThanOrEqual
+        )
+    }
+
+    /// Convert the operation to PDDL.
+    pub const fn to_pddl(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Equal => "=",
+            BinaryOp::LessThan => "<",
+            BinaryOp::GreaterThan => ">",
+            BinaryOp::LessThanOrEqual => "<=",
+            BinaryOp::GreaterThanOrEqual => ">=",
+        }
+    }
 }
 
 /// An enumeration of duration instants that can be used in expressions. The duration instant can be one of `at start`, `at end`, or `over all`.
@@ -37,6 +78,55 @@ pub enum DurationInstant {
     All,
 }
 
+/// A floating point number with a total order (see [`f64::total_cmp`]), so that the expressions containing one can implement [`Eq`], [`Ord`] and [`Hash`]. In particular, `-0.0` is smaller than `0.0`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(transparent)]
+pub struct OrderedFloat(pub f64);
+
+impl PartialEq for OrderedFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for OrderedFloat {}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for OrderedFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl From<f64> for OrderedFloat {
+    fn from(f: f64) -> Self {
+        Self(f)
+    }
+}
+
+impl From<OrderedFloat> for f64 {
+    fn from(f: OrderedFloat) -> Self {
+        f.0
+    }
+}
+
+impl std::fmt::Display for OrderedFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// An enumeration of expressions that can be used in PDDL planning domains and problems.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Expression {
@@ -48,8 +138,10 @@ pub enum Expression {
         #[serde(default)]
         parameters: Vec<Parameter>,
     },
-    /// A logical "and" expression that takes a list of sub-expressions as arguments.
+    /// A logical "and" expression that takes a list of sub-expressions as arguments. An empty "and" is trivially true.
     And(Vec<Expression>),
+    /// A logical "or" expression that takes a list of sub-expressions as arguments. An empty "or" is trivially false.
+    Or(Vec<Expression>),
     /// A logical "not" expression that takes a single sub-expression as an argument.
     Not(Box<Expression>),
 
@@ -68,14 +160,48 @@ pub enum Expression {
     BinaryOp(BinaryOp, Box<Expression>, Box<Expression>),
     /// A numeric constant expression.
     Number(i64),
+    /// A floating point numeric constant expression (e.g. `2.5`).
+    Float(OrderedFloat),
+    /// A term used as an operand: a variable (e.g. `?n` in `(increase (count) ?n)`) or an object (e.g. `table` in `(= ?x table)`, as opposed to the zero-ary atom `(table)`).
+    Term(Parameter),
 
-    // Forall
+    // Quantifiers
     /// A forall expression that takes a list of typed parameters and a sub-expression as arguments.
     Forall(Vec<TypedParameter>, Box<Expression>),
+    /// An exists expression that takes a list of typed parameters and a sub-expression as arguments.
+    Exists(Vec<TypedParameter>, Box<Expression>),
+
+    // Conditional effects
+    /// A conditional effect that takes a condition and an effect as arguments. The effect only applies if the condition holds.
+    When(Box<Expression>, Box<Expression>),
 
     // Duration
     /// A duration expression that takes a duration instant and a sub-expression as arguments. The duration instant can be one of `at start`, `at end`, or `over all`.
     Duration(DurationInstant, Box<Expression>),
+    /// A timed initial literal (PDDL 2.2), `(at t literal)`, that makes the literal true at the time point `t`. It can only appear in the initial state of a problem.
+    TimedLiteral(OrderedFloat, Box<Expression>),
+
+    // Constraints
+    /// A preference expression that takes an optional name and a sub-expression (a goal or a constraint) as arguments.
+    Preference(Option<String>, Box<Expression>),
+    /// An `always` constraint that requires the sub-expression to be true in every state of the plan.
+    Always(Box<Expression>),
+    /// A `sometime` constraint that requires the sub-expression to be true at some point of the plan.
+    Sometime(Box<Expression>),
+    /// A `within` constraint that requires the sub-expression to be true at some point no later than the time point.
+    Within(OrderedFloat, Box<Expression>),
+    /// An `at-most-once` constraint that requires the sub-expression to become true at most once, i.e. once it stops being true, it stays false.
+    AtMostOnce(Box<Expression>),
+    /// A `sometime-after` constraint that requires the second sub-expression to be true at the same time as or after every state where the first one is true.
+    SometimeAfter(Box<Expression>, Box<Expression>),
+    /// A `sometime-before` constraint that requires the second sub-expression to have been true strictly before every state where the first one is true.
+    SometimeBefore(Box<Expression>, Box<Expression>),
+    /// An `always-within` constraint that requires the third sub-expression to be true within the time bound every time the second one is true.
+    AlwaysWithin(OrderedFloat, Box<Expression>, Box<Expression>),
+    /// A `hold-during` constraint that requires the sub-expression to be true between the two time points (the first one included, the second one excluded).
+    HoldDuring(OrderedFloat, OrderedFloat, Box<Expression>),
+    /// A `hold-after` constraint that requires the sub-expression to be true at some point after the time point.
+    HoldAfter(OrderedFloat, Box<Expression>),
 }
 
 impl Expression {
@@ -84,7 +210,11 @@ impl Expression {
         log::debug!("BEGIN > parse_expression {:?}", input.span());
         let (output, expression) = alt((
             Self::parse_and,
+            Self::parse_or,
             Self::parse_not,
+            Self::parse_float,
+            // Atoms only start with an identifier (or a modal keyword followed by parameters), so they cannot shadow
+            // comparisons or quantifiers, e.g. in `(not (>= (fuel ?v) 10))` or `(not (forall (?x) (p ?x)))`.
             Self::parse_atom,
             // Assign op
             alt((
@@ -95,8 +225,24 @@ impl Expression {
                 Self::parse_decrease,
             )),
             Self::parse_duration,
+            Self::parse_timed_literal,
             Self::parse_forall,
+            Self::parse_exists,
+            Self::parse_when,
             Self::parse_comparison,
+            // Constraints
+            alt((
+                Self::parse_preference,
+                Self::parse_always,
+                Self::parse_sometime,
+                Self::parse_within,
+                Self::parse_at_most_once,
+                Self::parse_sometime_after,
+                Self::parse_sometime_before,
+                Self::parse_always_within,
+                Self::parse_hold_during,
+                Self::parse_hold_after,
+            )),
         ))(input)?;
         log::debug!("END < parse_expression {:?}", output.span());
         Ok((output, expression))
@@ -110,14 +256,8 @@ impl Expression {
                 name,
                 parameters.iter().map(Parameter::to_pddl).collect::<Vec<_>>().join(" ")
             ),
-            Expression::And(expressions) => format!(
-                "(and {})",
-                expressions
-                    .iter()
-                    .map(Expression::to_pddl)
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            ),
+            Expression::And(expressions) => Self::to_pddl_list("and", expressions),
+            Expression::Or(expressions) => Self::to_pddl_list("or", expressions),
             Expression::Not(expression) => format!("(not {})", expression.to_pddl()),
             Expression::Assign(exp1, exp2) => format!("(assign {} {})", exp1.to_pddl(), exp2.to_pddl()),
             Expression::Increase(exp1, exp2) => {
@@ -132,19 +272,12 @@ impl Expression {
             Expression::ScaleDown(exp1, exp2) => {
                 format!("(scale-down {} {})", exp1.to_pddl(), exp2.to_pddl())
             },
-            Expression::BinaryOp(op, exp1, exp2) => format!(
-                "({} {} {})",
-                match op {
-                    BinaryOp::Add => "+",
-                    BinaryOp::Subtract => "-",
-                    BinaryOp::Multiply => "*",
-                    BinaryOp::Divide => "/",
-                    BinaryOp::Equal => "=",
-                },
-                exp1.to_pddl(),
-                exp2.to_pddl()
-            ),
+            Expression::BinaryOp(op, exp1, exp2) => {
+                format!("({} {} {})", op.to_pddl(), exp1.to_pddl(), exp2.to_pddl())
+            },
             Expression::Number(n) => n.to_string(),
+            Expression::Float(f) => Self::float_to_pddl(*f),
+            Expression::Term(term) => term.to_pddl(),
             Expression::Duration(instant, exp) => format!(
                 "({} {})",
                 match instant {
@@ -163,6 +296,558 @@ impl Expression {
                     .join(" "),
                 expression.to_pddl()
             ),
+            Expression::Exists(parameters, expression) => format!(
+                "(exists ({}) {})",
+                parameters
+                    .iter()
+                    .map(TypedParameter::to_pddl)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                expression.to_pddl()
+            ),
+            Expression::TimedLiteral(time, expression) => {
+                format!("(at {} {})", Self::float_to_pddl(*time), expression.to_pddl())
+            },
+            Expression::When(condition, effect) => format!("(when {} {})", condition.to_pddl(), effect.to_pddl()),
+            Expression::Preference(name, expression) => match name {
+                Some(name) => format!("(preference {} {})", name, expression.to_pddl()),
+                None => format!("(preference {})", expression.to_pddl()),
+            },
+            Expression::Always(expression) => format!("(always {})", expression.to_pddl()),
+            Expression::Sometime(expression) => format!("(sometime {})", expression.to_pddl()),
+            Expression::Within(time, expression) => {
+                format!("(within {} {})", Self::float_to_pddl(*time), expression.to_pddl())
+            },
+            Expression::AtMostOnce(expression) => format!("(at-most-once {})", expression.to_pddl()),
+            Expression::SometimeAfter(exp1, exp2) => {
+                format!("(sometime-after {} {})", exp1.to_pddl(), exp2.to_pddl())
+            },
+            Expression::SometimeBefore(exp1, exp2) => {
+                format!("(sometime-before {} {})", exp1.to_pddl(), exp2.to_pddl())
+            },
+            Expression::AlwaysWithin(time, exp1, exp2) => format!(
+                "(always-within {} {} {})",
+                Self::float_to_pddl(*time),
+                exp1.to_pddl(),
+                exp2.to_pddl()
+            ),
+            Expression::HoldDuring(start, end, expression) => format!(
+                "(hold-during {} {} {})",
+                Self::float_to_pddl(*start),
+                Self::float_to_pddl(*end),
+                expression.to_pddl()
+            ),
+            Expression::HoldAfter(time, expression) => {
+                format!("(hold-after {} {})", Self::float_to_pddl(*time), expression.to_pddl())
+            },
+        }
+    }
+
+    /// Convert the expression to PDDL, indenting the nested logical expressions (e.g. `and`, `not`, `forall` and `when`) by depth, with one sub-expression per line:
+    ///
+    /// ```text
+    /// (and
+    ///   (on ?arm ?loc)
+    ///   (forall (?b - box)
+    ///     (when (on ?b ?loc) (not (free ?b)))))
+    /// ```
+    ///
+    /// An expression is only split over several lines if it is a conjunction or disjunction of several sub-expressions, or if one of its sub-expressions is itself a logical expression, so atoms, comparisons and numeric effects stay on one line. The output parses back to the same expression as [`Expression::to_pddl`].
+    pub fn to_pddl_pretty(&self) -> String {
+        self.to_pddl_indented(0)
+    }
+
+    fn to_pddl_indented(&self, depth: usize) -> String {
+        let Some(keyword) = self.logical_keyword()
+        else {
+            return self.to_pddl();
+        };
+        let children = self.children();
+        let nested = children.iter().any(|child| child.logical_keyword().is_some());
+        let junction = matches!(self, Expression::And(_) | Expression::Or(_)) && children.len() > 1;
+        if !nested && !junction {
+            return self.to_pddl();
+        }
+        let indent = "  ".repeat(depth + 1);
+        let children = children
+            .iter()
+            .map(|child| format!("\n{indent}{}", child.to_pddl_indented(depth + 1)))
+            .collect::<String>();
+        format!("({keyword}{children})")
+    }
+
+    /// Returns the keyword (and the arguments that are not sub-expressions) of a logical expression, i.e. an expression whose arguments are all logical sub-expressions, like `and` or `at start`. Returns `None` for atoms and numeric expressions.
+    fn logical_keyword(&self) -> Option<String> {
+        let parameters = |parameters: &[TypedParameter]| {
+            parameters.iter().map(TypedParameter::to_pddl).collect::<Vec<_>>().join(" ")
+        };
+        match self {
+            Expression::Atom { .. }
+            | Expression::Assign(_, _)
+            | Expression::Increase(_, _)
+            | Expression::Decrease(_, _)
+            | Expression::ScaleUp(_, _)
+            | Expression::ScaleDown(_, _)
+            | Expression::BinaryOp(_, _, _)
+            | Expression::Number(_)
+            | Expression::Float(_)
+            | Expression::Term(_) => None,
+            Expression::And(_) => Some("and".to_string()),
+            Expression::Or(_) => Some("or".to_string()),
+            Expression::Not(_) => Some("not".to_string()),
+            Expression::Forall(variables, _) => Some(format!("forall ({})", parameters(variables))),
+            Expression::Exists(variables, _) => Some(format!("exists ({})", parameters(variables))),
+            Expression::When(_, _) => Some("when".to_string()),
+            Expression::Duration(instant, _) => Some(
+                match instant {
+                    DurationInstant::Start => "at start",
+                    DurationInstant::End => "at end",
+                    DurationInstant::All => "over all",
+                }
+                .to_string(),
+            ),
+            Expression::TimedLiteral(time, _) => Some(format!("at {}", Self::float_to_pddl(*time))),
+            Expression::Preference(name, _) => Some(match name {
+                Some(name) => format!("preference {name}"),
+                None => "preference".to_string(),
+            }),
+            Expression::Always(_) => Some("always".to_string()),
+            Expression::Sometime(_) => Some("sometime".to_string()),
+            Expression::Within(time, _) => Some(format!("within {}", Self::float_to_pddl(*time))),
+            Expression::AtMostOnce(_) => Some("at-most-once".to_string()),
+            Expression::SometimeAfter(_, _) => Some("sometime-after".to_string()),
+            Expression::SometimeBefore(_, _) => Some("sometime-before".to_string()),
+            Expression::AlwaysWithin(time, _, _) => Some(format!("always-within {}", Self::float_to_pddl(*time))),
+            Expression::HoldDuring(start, end, _) => Some(format!(
+                "hold-during {} {}",
+                Self::float_to_pddl(*start),
+                Self::float_to_pddl(*end)
+            )),
+            Expression::HoldAfter(time, _) => Some(format!("hold-after {}", Self::float_to_pddl(*time))),
+        }
+    }
+
+    /// Get the direct sub-expressions of the expression.
+    pub(crate) fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Atom { .. } | Expression::Number(_) | Expression::Float(_) | Expression::Term(_) => vec![],
+            Expression::And(expressions) | Expression::Or(expressions) => expressions.iter().collect(),
+            Expression::Not(expression)
+            | Expression::Forall(_, expression)
+            | Expression::Exists(_, expression)
+            | Expression::Duration(_, expression)
+            | Expression::TimedLiteral(_, expression)
+            | Expression::Preference(_, expression)
+            | Expression::Always(expression)
+            | Expression::Sometime(expression)
+            | Expression::Within(_, expression)
+            | Expression::AtMostOnce(expression)
+            | Expression::HoldDuring(_, _, expression)
+            | Expression::HoldAfter(_, expression) => vec![expression],
+            Expression::Assign(exp1, exp2)
+            | Expression::Increase(exp1, exp2)
+            | Expression::Decrease(exp1, exp2)
+            | Expression::ScaleUp(exp1, exp2)
+            | Expression::ScaleDown(exp1, exp2)
+            | Expression::When(exp1, exp2)
+            | Expression::SometimeAfter(exp1, exp2)
+            | Expression::SometimeBefore(exp1, exp2)
+            | Expression::AlwaysWithin(_, exp1, exp2)
+            | Expression::BinaryOp(_, exp1, exp2) => vec![exp1, exp2],
+        }
+    }
+
+    /// Simplify the expression, preserving its semantics.
+    ///
+    /// The simplification is applied bottom-up and:
+    /// - flattens nested `and`/`or` expressions,
+    /// - folds arithmetic operations and comparisons between numeric literals,
+    /// - removes trivially true conjuncts and trivially false disjuncts, and collapses an `and` with a trivially false conjunct (resp. an `or` with a trivially true disjunct),
+    /// - drops double negations,
+    /// - collapses `and`/`or` expressions with a single sub-expression.
+    ///
+    /// Simplifying an already simplified expression returns it unchanged.
+    pub fn simplify(self) -> Expression {
+        self.simplified()
+    }
+
+    fn simplified(&self) -> Expression {
+        match self.map_children(Expression::simplified) {
+            Expression::And(expressions) => Self::simplify_junction(expressions, true),
+            Expression::Or(expressions) => Self::simplify_junction(expressions, false),
+            Expression::Not(expression) => match *expression {
+                Expression::Not(expression) => *expression,
+                expression if expression.is_trivially_true() => Expression::Or(vec![]),
+                expression if expression.is_trivially_false() => Expression::And(vec![]),
+                expression => Expression::Not(Box::new(expression)),
+            },
+            Expression::BinaryOp(op, exp1, exp2) => match (&*exp1, &*exp2) {
+                (Expression::Number(n1), Expression::Number(n2)) => {
+                    Self::fold(&op, *n1, *n2).unwrap_or(Expression::BinaryOp(op, exp1, exp2))
+                },
+                _ => Expression::BinaryOp(op, exp1, exp2),
+            },
+            expression => expression,
+        }
+    }
+
+    fn simplify_junction(expressions: Vec<Expression>, conjunction: bool) -> Expression {
+        let mut flattened = Vec::new();
+        for expression in expressions {
+            match expression {
+                Expression::And(expressions) if conjunction => flattened.extend(expressions),
+                Expression::Or(expressions) if !conjunction => flattened.extend(expressions),
+                expression => flattened.push(expression),
+            }
+        }
+        let absorbing = |expression: &Expression| {
+            if conjunction {
+                expression.is_trivially_false()
+            }
+            else {
+                expression.is_trivially_true()
+            }
+        };
+        if flattened.iter().any(absorbing) {
+            return if conjunction {
+                Expression::Or(vec![])
+            }
+            else {
+                Expression::And(vec![])
+            };
+        }
+        if flattened.len() == 1 {
+            return flattened.remove(0);
+        }
+        if conjunction {
+            Expression::And(flattened)
+        }
+        else {
+            Expression::Or(flattened)
+        }
+    }
+
+    fn fold(op: &BinaryOp, n1: i64, n2: i64) -> Option<Expression> {
+        let boolean = |value: bool| {
+            if value {
+                Expression::And(vec![])
+            }
+            else {
+                Expression::Or(vec![])
+            }
+        };
+        match op {
+            BinaryOp::Add => n1.checked_add(n2).map(Expression::Number),
+            BinaryOp::Subtract => n1.checked_sub(n2).map(Expression::Number),
+            BinaryOp::Multiply => n1.checked_mul(n2).map(Expression::Number),
+            BinaryOp::Divide => match n1.checked_rem(n2) {
+                Some(0) => n1.checked_div(n2).map(Expression::Number),
+                _ => None,
+            },
+            BinaryOp::Equal => Some(boolean(n1 == n2)),
+            BinaryOp::LessThan => Some(boolean(n1 < n2)),
+            BinaryOp::GreaterThan => Some(boolean(n1 > n2)),
+            BinaryOp::LessThanOrEqual => Some(boolean(n1 <= n2)),
+            BinaryOp::GreaterThanOrEqual => Some(boolean(n1 >= n2)),
+        }
+    }
+
+    /// Collect every numeric literal of the expression, in the order they appear.
+    pub fn collect_numbers(&self) -> Vec<f64> {
+        let mut numbers = Vec::new();
+        self.collect_numbers_into(&mut numbers);
+        numbers
+    }
+
+    /// Collect every ground atom of the expression, i.e. the atoms whose parameters are all objects (none of them is a variable), in the order they appear.
+    pub fn ground_atoms(&self) -> Vec<&Expression> {
+        let mut atoms = Vec::new();
+        self.ground_atoms_into(&mut atoms);
+        atoms
+    }
+
+    /// Call `f` on the expression and on each of its sub-expressions, recursively, in pre-order (each node is visited before its children, and the children are visited in the order they appear).
+    pub fn visit<'a, F: FnMut(&'a Expression)>(&'a self, f: &mut F) {
+        f(self);
+        for child in self.children() {
+            child.visit(f);
+        }
+    }
+
+    /// Iterate over the name and parameters of every atom of the expression, in the order they appear, including the function applications of numeric expressions (e.g. `(fuel ?v)` in `(decrease (fuel ?v) 1)`). Variables used as terms (e.g. `?d` in `(>= ?d 0)`) are not atoms, so they are skipped.
+    pub fn atoms(&self) -> impl Iterator<Item = (&str, &[Parameter])> + '_ {
+        let mut atoms = Vec::new();
+        self.visit(&mut |expression| {
+            if let Expression::Atom { name, parameters } = expression {
+                atoms.push((name.as_str(), parameters.as_slice()));
+            }
+        });
+        atoms.into_iter()
+    }
+
+    /// Collect the variables of the expression that are not bound by an enclosing `forall` or `exists`, in the order they are first seen and without duplicates. A variable bound by a quantifier is still free outside of it, even if the quantifier reuses the name of an outer variable.
+    pub fn free_variables(&self) -> Vec<String> {
+        let mut variables = Vec::new();
+        self.free_variables_into(&mut Vec::new(), &mut variables);
+        variables
+    }
+
+    fn free_variables_into<'a>(&'a self, bound: &mut Vec<&'a str>, variables: &mut Vec<String>) {
+        match self {
+            Expression::Atom { parameters, .. } => {
+                for variable in parameters.iter().filter(|term| term.is_variable()).map(Parameter::as_str) {
+                    if !bound.contains(&variable) && !variables.iter().any(|v| v == variable) {
+                        variables.push(variable.to_string());
+                    }
+                }
+            },
+            Expression::Term(term) if term.is_variable() => {
+                let variable = term.as_str();
+                if !bound.contains(&variable) && !variables.iter().any(|v| v == variable) {
+                    variables.push(variable.to_string());
+                }
+            },
+            Expression::Forall(parameters, expression) | Expression::Exists(parameters, expression) => {
+                let scope = bound.len();
+                bound.extend(parameters.iter().map(|parameter| parameter.name.as_str()));
+                expression.free_variables_into(bound, variables);
+                bound.truncate(scope);
+            },
+            _ => {
+                for child in self.children() {
+                    child.free_variables_into(bound, variables);
+                }
+            },
+        }
+    }
+
+    /// Count how many atoms with the given name appear in the expression. Every occurrence is counted, so the same atom appearing twice counts twice.
+    pub fn occurrences_of(&self, name: &str) -> usize {
+        let own = usize::from(matches!(self, Expression::Atom { name: atom, .. } if atom == name));
+        own + self.children().into_iter().map(|child| child.occurrences_of(name)).sum::<usize>()
+    }
+
+    /// Render the expression as an indented tree, one node per line, for debugging. Each node is shown as its variant name followed by its own data (e.g. the name and parameters of an atom), and its sub-expressions are indented by two spaces below it:
+    ///
+    /// ```text
+    /// And
+    ///   Atom on [?arm ?loc]
+    ///   Not
+    ///     Atom holding [?arm]
+    /// ```
+    ///
+    /// Unlike [`Expression::to_pddl`], the tree shows the structure of the AST, which makes it easier to see why two expressions differ.
+    pub fn pretty_tree(&self) -> String {
+        let mut lines = Vec::new();
+        self.pretty_tree_into(0, &mut lines);
+        lines.join("\n")
+    }
+
+    fn pretty_tree_into(&self, depth: usize, lines: &mut Vec<String>) {
+        let label = match self {
+            Expression::Atom { name, parameters } => format!(
+                "Atom {name} [{}]",
+                parameters.iter().map(Parameter::to_pddl).collect::<Vec<_>>().join(" ")
+            ),
+            Expression::And(_) => "And".to_string(),
+            Expression::Or(_) => "Or".to_string(),
+            Expression::Not(_) => "Not".to_string(),
+            Expression::Assign(_, _) => "Assign".to_string(),
+            Expression::Increase(_, _) => "Increase".to_string(),
+            Expression::Decrease(_, _) => "Decrease".to_string(),
+            Expression::ScaleUp(_, _) => "ScaleUp".to_string(),
+            Expression::ScaleDown(_, _) => "ScaleDown".to_string(),
+            Expression::BinaryOp(op, _, _) => format!("BinaryOp {}", op.to_pddl()),
+            Expression::Number(n) => format!("Number {n}"),
+            Expression::Float(f) => format!("Float {f}"),
+            Expression::Term(term) => format!("Term {}", term.as_str()),
+            Expression::Forall(parameters, _) => format!(
+                "Forall [{}]",
+                parameters.iter().map(TypedParameter::to_pddl).collect::<Vec<_>>().join(" ")
+            ),
+            Expression::Exists(parameters, _) => format!(
+                "Exists [{}]",
+                parameters.iter().map(TypedParameter::to_pddl).collect::<Vec<_>>().join(" ")
+            ),
+            Expression::When(_, _) => "When".to_string(),
+            Expression::Duration(instant, _) => format!("Duration {instant:?}"),
+            Expression::TimedLiteral(time, _) => format!("TimedLiteral {time}"),
+            Expression::Preference(name, _) => match name {
+                Some(name) => format!("Preference {name}"),
+                None => "Preference".to_string(),
+            },
+            Expression::Always(_) => "Always".to_string(),
+            Expression::Sometime(_) => "Sometime".to_string(),
+            Expression::Within(time, _) => format!("Within {time}"),
+            Expression::AtMostOnce(_) => "AtMostOnce".to_string(),
+            Expression::SometimeAfter(_, _) => "SometimeAfter".to_string(),
+            Expression::SometimeBefore(_, _) => "SometimeBefore".to_string(),
+            Expression::AlwaysWithin(time, _, _) => format!("AlwaysWithin {time}"),
+            Expression::HoldDuring(from, to, _) => format!("HoldDuring {from} {to}"),
+            Expression::HoldAfter(time, _) => format!("HoldAfter {time}"),
+        };
+        lines.push(format!("{}{label}", "  ".repeat(depth)));
+        for child in self.children() {
+            child.pretty_tree_into(depth + 1, lines);
+        }
+    }
+
+    fn ground_atoms_into<'a>(&'a self, atoms: &mut Vec<&'a Expression>) {
+        if let Expression::Atom { parameters, .. } = self {
+            if !parameters.iter().any(Parameter::is_variable) {
+                atoms.push(self);
+            }
+        }
+        for child in self.children() {
+            child.ground_atoms_into(atoms);
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn collect_numbers_into(&self, numbers: &mut Vec<f64>) {
+        match self {
+            Expression::Number(n) => numbers.push(*n as f64),
+            Expression::Float(f) => numbers.push(f.0),
+            _ => {},
+        }
+        for child in self.children() {
+            child.collect_numbers_into(numbers);
+        }
+    }
+
+    /// Rebuild the expression, replacing each direct sub-expression with the result of `f`.
+    pub(crate) fn map_children<F: FnMut(&Expression) -> Expression>(&self, mut f: F) -> Expression {
+        match self {
+            Expression::Atom { .. } | Expression::Number(_) | Expression::Float(_) | Expression::Term(_) => {
+                self.clone()
+            },
+            Expression::And(expressions) => Expression::And(expressions.iter().map(f).collect()),
+            Expression::Or(expressions) => Expression::Or(expressions.iter().map(f).collect()),
+            Expression::Not(expression) => Expression::Not(Box::new(f(expression))),
+            Expression::Assign(exp1, exp2) => Expression::Assign(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::Increase(exp1, exp2) => Expression::Increase(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::Decrease(exp1, exp2) => Expression::Decrease(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::ScaleUp(exp1, exp2) => Expression::ScaleUp(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::ScaleDown(exp1, exp2) => Expression::ScaleDown(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::BinaryOp(op, exp1, exp2) => {
+                Expression::BinaryOp(op.clone(), Box::new(f(exp1)), Box::new(f(exp2)))
+            },
+            Expression::Forall(parameters, expression) => {
+                Expression::Forall(parameters.clone(), Box::new(f(expression)))
+            },
+            Expression::Exists(parameters, expression) => {
+                Expression::Exists(parameters.clone(), Box::new(f(expression)))
+            },
+            Expression::When(condition, effect) => Expression::When(Box::new(f(condition)), Box::new(f(effect))),
+            Expression::Duration(instant, expression) => {
+                Expression::Duration(instant.clone(), Box::new(f(expression)))
+            },
+            Expression::TimedLiteral(time, expression) => Expression::TimedLiteral(*time, Box::new(f(expression))),
+            Expression::Preference(name, expression) => {
+                Expression::Preference(name.clone(), Box::new(f(expression)))
+            },
+            Expression::Always(expression) => Expression::Always(Box::new(f(expression))),
+            Expression::Sometime(expression) => Expression::Sometime(Box::new(f(expression))),
+            Expression::Within(time, expression) => Expression::Within(*time, Box::new(f(expression))),
+            Expression::AtMostOnce(expression) => Expression::AtMostOnce(Box::new(f(expression))),
+            Expression::SometimeAfter(exp1, exp2) => Expression::SometimeAfter(Box::new(f(exp1)), Box::new(f(exp2))),
+            Expression::SometimeBefore(exp1, exp2) => {
+                Expression::SometimeBefore(Box::new(f(exp1)), Box::new(f(exp2)))
+            },
+            Expression::AlwaysWithin(time, exp1, exp2) => {
+                Expression::AlwaysWithin(*time, Box::new(f(exp1)), Box::new(f(exp2)))
+            },
+            Expression::HoldDuring(start, end, expression) => {
+                Expression::HoldDuring(*start, *end, Box::new(f(expression)))
+            },
+            Expression::HoldAfter(time, expression) => Expression::HoldAfter(*time, Box::new(f(expression))),
+        }
+    }
+
+    /// Replace the variables of the expression with the objects they are bound to, both in the parameters of atoms and as terms (e.g. `?x` in `(= ?x ?y)`). Variables bound by an inner quantifier and variables without a binding (e.g. `?duration`) are left intact.
+    pub(crate) fn substitute(&self, bindings: &HashMap<String, String>) -> Expression {
+        match self {
+            Expression::Term(term) => bindings
+                .get(term.as_str())
+                .map_or_else(|| self.clone(), |object| Expression::Term(object.as_str().into())),
+            Expression::Atom { name, parameters } => Expression::Atom {
+                name: name.clone(),
+                parameters: parameters
+                    .iter()
+                    .map(|parameter| {
+                        bindings
+                            .get(parameter.as_str())
+                            .map_or_else(|| parameter.clone(), |object| object.as_str().into())
+                    })
+                    .collect(),
+            },
+            Expression::Forall(parameters, _) | Expression::Exists(parameters, _) => {
+                let mut bindings = bindings.clone();
+                for parameter in parameters {
+                    bindings.remove(&parameter.name);
+                }
+                self.map_children(|expression| expression.substitute(&bindings))
+            },
+            _ => self.map_children(|expression| expression.substitute(bindings)),
+        }
+    }
+
+    fn to_pddl_list(keyword: &str, expressions: &[Expression]) -> String {
+        if expressions.is_empty() {
+            format!("({keyword})")
+        }
+        else {
+            format!(
+                "({} {})",
+                keyword,
+                expressions
+                    .iter()
+                    .map(Expression::to_pddl)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        }
+    }
+
+    /// Convert the expression to negation normal form, i.e. push the negations inwards until they only apply to atoms (and to the expressions that have no dual, like comparisons).
+    ///
+    /// De Morgan's laws are applied to `and`/`or`, double negations are removed, and a negated `forall` (resp. `exists`) becomes an `exists` (resp. `forall`) of the negated sub-expression.
+    pub fn to_nnf(&self) -> Expression {
+        match self {
+            Expression::Not(expression) => expression.negated_nnf(),
+            _ => self.map_children(Expression::to_nnf),
+        }
+    }
+
+    /// Returns the negation normal form of `(not self)`.
+    fn negated_nnf(&self) -> Expression {
+        match self {
+            Expression::Not(expression) => expression.to_nnf(),
+            Expression::And(expressions) => Expression::Or(expressions.iter().map(Expression::negated_nnf).collect()),
+            Expression::Or(expressions) => Expression::And(expressions.iter().map(Expression::negated_nnf).collect()),
+            Expression::Forall(parameters, expression) => {
+                Expression::Exists(parameters.clone(), Box::new(expression.negated_nnf()))
+            },
+            Expression::Exists(parameters, expression) => {
+                Expression::Forall(parameters.clone(), Box::new(expression.negated_nnf()))
+            },
+            _ => Expression::Not(Box::new(self.to_nnf())),
+        }
+    }
+
+    /// Returns `true` if the expression is trivially true, that is, an empty `(and)` or an `and` whose sub-expressions are all trivially true.
+    pub fn is_trivially_true(&self) -> bool {
+        match self {
+            Expression::And(expressions) => expressions.iter().all(Expression::is_trivially_true),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the expression is trivially false, that is, an empty `(or)` or an `or` whose sub-expressions are all trivially false.
+    pub fn is_trivially_false(&self) -> bool {
+        match self {
+            Expression::Or(expressions) => expressions.iter().all(Expression::is_trivially_false),
+            _ => false,
         }
     }
 
@@ -177,6 +862,20 @@ impl Expression {
         Ok((output, Expression::And(expressions)))
     }
 
+    fn parse_or(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_or {:?}", input.span());
+        let (output, expressions) = delimited(
+            Token::OpenParen,
+            preceded(
+                pair(Token::Or, requires(&[Requirement::DisjunctivePreconditions])),
+                many0(Expression::parse_expression),
+            ),
+            Token::CloseParen,
+        )(input)?;
+        log::debug!("END < parse_or {:?}", output.span());
+        Ok((output, Expression::Or(expressions)))
+    }
+
     fn parse_not(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
         log::debug!("BEGIN > parse_not {:?}", input.span());
         let (output, expression) = delimited(
@@ -193,7 +892,7 @@ impl Expression {
         let (output, expression) = map(
             delimited(
                 Token::OpenParen,
-                pair(id, Parameter::parse_parameters),
+                pair(predicate_name, Parameter::parse_parameters),
                 Token::CloseParen,
             ),
             |(name, parameters)| Expression::Atom { name, parameters },
@@ -202,27 +901,34 @@ impl Expression {
         Ok((output, expression))
     }
 
-    fn parse_var(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
-        log::debug!("BEGIN > parse_var {:?}", input.span());
-        let (output, expression) = map(var, |name| Expression::Atom {
-            name,
-            parameters: Vec::new(),
-        })(input)?;
-        log::debug!("END < parse_var {:?}", output.span());
+    fn parse_term(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_term {:?}", input.span());
+        let (output, expression) = map(alt((var, id)), |name| Expression::Term(name.into()))(input)?;
+        log::debug!("END < parse_term {:?}", output.span());
         Ok((output, expression))
     }
 
+    /// Parse an operand of a comparison or a numeric effect: a number, an arithmetic operation or a comparison, a function application, a variable or an object.
+    fn parse_operand(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_operand {:?}", input.span());
+        let (output, operand) = alt((
+            Self::parse_number,
+            Self::parse_comparison,
+            Self::parse_atom,
+            Self::parse_term,
+        ))(input)?;
+        log::debug!("END < parse_operand {:?}", output.span());
+        Ok((output, operand))
+    }
+
     fn parse_assign(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
         log::debug!("BEGIN > parse_assign {:?}", input.span());
         let (output, expression) = map(
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::Assign,
-                    tuple((
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                    )),
+                    pair(Token::Assign, requires(&[Requirement::NumericFluents])),
+                    tuple((Self::parse_operand, Self::parse_operand)),
                 ),
                 Token::CloseParen,
             ),
@@ -240,6 +946,10 @@ impl Expression {
             map(Token::Times, |_| BinaryOp::Multiply),
             map(Token::Divide, |_| BinaryOp::Divide),
             map(Token::Equal, |_| BinaryOp::Equal),
+            map(Token::Less, |_| BinaryOp::LessThan),
+            map(Token::Greater, |_| BinaryOp::GreaterThan),
+            map(Token::LessEqual, |_| BinaryOp::LessThanOrEqual),
+            map(Token::GreaterEqual, |_| BinaryOp::GreaterThanOrEqual),
         ))(input)?;
         log::debug!("END < parse_binary_operator {:?}", output.span());
         Ok((output, op))
@@ -252,18 +962,8 @@ impl Expression {
                 Token::OpenParen,
                 tuple((
                     Self::parse_binary_operator,
-                    alt((
-                        Self::parse_number,
-                        Self::parse_comparison,
-                        Self::parse_atom,
-                        Self::parse_var,
-                    )),
-                    alt((
-                        Self::parse_number,
-                        Self::parse_comparison,
-                        Self::parse_atom,
-                        Self::parse_var,
-                    )),
+                    Self::parse_operand,
+                    Self::parse_operand,
                 )),
                 Token::CloseParen,
             ),
@@ -275,9 +975,27 @@ impl Expression {
 
     fn parse_number(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
         log::debug!("BEGIN > parse_number {:?}", input.span());
-        let (output, number) = integer(input)?;
+        let (output, number) = alt((Self::parse_float, map(integer, Expression::Number)))(input)?;
         log::debug!("END < parse_number {:?}", output.span());
-        Ok((output, Expression::Number(number)))
+        Ok((output, number))
+    }
+
+    fn parse_float(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_float {:?}", input.span());
+        let (output, number) = float(input)?;
+        log::debug!("END < parse_float {:?}", output.span());
+        Ok((output, Expression::Float(OrderedFloat(number))))
+    }
+
+    /// Format a floating point number: a literal, a time point or a time bound. Integral values are emitted without a fractional part (e.g. `10` instead of `10.0`), so a literal such as `2.0` is parsed back as an [`Expression::Number`].
+    #[allow(clippy::cast_possible_truncation)]
+    fn float_to_pddl(f: OrderedFloat) -> String {
+        if f.0.fract() == 0.0 && f.0.abs() < 1e15 {
+            (f.0 as i64).to_string()
+        }
+        else {
+            f.to_string()
+        }
     }
 
     fn parse_scale_up(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
@@ -286,11 +1004,8 @@ impl Expression {
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::ScaleUp,
-                    tuple((
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                    )),
+                    pair(Token::ScaleUp, requires(&[Requirement::NumericFluents])),
+                    tuple((Self::parse_operand, Self::parse_operand)),
                 ),
                 Token::CloseParen,
             ),
@@ -306,11 +1021,8 @@ impl Expression {
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::ScaleDown,
-                    tuple((
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                    )),
+                    pair(Token::ScaleDown, requires(&[Requirement::NumericFluents])),
+                    tuple((Self::parse_operand, Self::parse_operand)),
                 ),
                 Token::CloseParen,
             ),
@@ -326,11 +1038,11 @@ impl Expression {
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::Increase,
-                    tuple((
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                    )),
+                    pair(
+                        Token::Increase,
+                        requires(&[Requirement::NumericFluents, Requirement::ActionCosts]),
+                    ),
+                    tuple((Self::parse_operand, Self::parse_operand)),
                 ),
                 Token::CloseParen,
             ),
@@ -346,11 +1058,8 @@ impl Expression {
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::Decrease,
-                    tuple((
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                        alt((Self::parse_number, Self::parse_comparison, Self::parse_atom)),
-                    )),
+                    pair(Token::Decrease, requires(&[Requirement::NumericFluents])),
+                    tuple((Self::parse_operand, Self::parse_operand)),
                 ),
                 Token::CloseParen,
             ),
@@ -366,7 +1075,10 @@ impl Expression {
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::Forall,
+                    pair(
+                        Token::Forall,
+                        requires(&[Requirement::UniversalPreconditions, Requirement::ConditionalEffects]),
+                    ),
                     tuple((
                         delimited(
                             Token::OpenParen,
@@ -384,6 +1096,47 @@ impl Expression {
         Ok((output, expression))
     }
 
+    fn parse_exists(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_exists {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    pair(Token::Exists, requires(&[Requirement::ExistentialPreconditions])),
+                    tuple((
+                        delimited(
+                            Token::OpenParen,
+                            TypedParameter::parse_typed_parameters,
+                            Token::CloseParen,
+                        ),
+                        Expression::parse_expression,
+                    )),
+                ),
+                Token::CloseParen,
+            ),
+            |(parameters, expression)| Expression::Exists(parameters, Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_exists {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_when(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_when {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    pair(Token::When, requires(&[Requirement::ConditionalEffects])),
+                    tuple((Expression::parse_expression, Expression::parse_expression)),
+                ),
+                Token::CloseParen,
+            ),
+            |(condition, effect)| Expression::When(Box::new(condition), Box::new(effect)),
+        )(input)?;
+        log::debug!("END < parse_when {:?}", output.span());
+        Ok((output, expression))
+    }
+
     fn parse_duration(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
         log::debug!("BEGIN > parse_duration {:?}", input.span());
         let (output, expression) = delimited(
@@ -407,4 +1160,187 @@ impl Expression {
         log::debug!("END < parse_duration {:?}", output.span());
         Ok((output, expression))
     }
+
+    fn parse_timed_literal(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_timed_literal {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    Token::At,
+                    tuple((
+                        number,
+                        requires(&[Requirement::TimedInitialLiterals]),
+                        Expression::parse_expression,
+                    )),
+                ),
+                Token::CloseParen,
+            ),
+            |(time, (), expression)| Expression::TimedLiteral(OrderedFloat(time), Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_timed_literal {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_preference(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_preference {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    pair(Token::Preference, requires(&[Requirement::Preferences])),
+                    pair(opt(id), Expression::parse_expression),
+                ),
+                Token::CloseParen,
+            ),
+            |(name, expression)| Expression::Preference(name, Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_preference {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_always(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_always {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::Always, Expression::parse_expression),
+                Token::CloseParen,
+            ),
+            |expression| Expression::Always(Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_always {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_sometime(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_sometime {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::Sometime, Expression::parse_expression),
+                Token::CloseParen,
+            ),
+            |expression| Expression::Sometime(Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_sometime {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_within(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_within {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::Within, pair(number, Expression::parse_expression)),
+                Token::CloseParen,
+            ),
+            |(time, expression)| Expression::Within(OrderedFloat(time), Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_within {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_at_most_once(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_at_most_once {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::AtMostOnce, Expression::parse_expression),
+                Token::CloseParen,
+            ),
+            |expression| Expression::AtMostOnce(Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_at_most_once {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_sometime_after(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_sometime_after {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    Token::SometimeAfter,
+                    pair(Expression::parse_expression, Expression::parse_expression),
+                ),
+                Token::CloseParen,
+            ),
+            |(exp1, exp2)| Expression::SometimeAfter(Box::new(exp1), Box::new(exp2)),
+        )(input)?;
+        log::debug!("END < parse_sometime_after {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_sometime_before(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_sometime_before {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    Token::SometimeBefore,
+                    pair(Expression::parse_expression, Expression::parse_expression),
+                ),
+                Token::CloseParen,
+            ),
+            |(exp1, exp2)| Expression::SometimeBefore(Box::new(exp1), Box::new(exp2)),
+        )(input)?;
+        log::debug!("END < parse_sometime_before {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_always_within(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_always_within {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(
+                    Token::AlwaysWithin,
+                    tuple((number, Expression::parse_expression, Expression::parse_expression)),
+                ),
+                Token::CloseParen,
+            ),
+            |(time, exp1, exp2)| {
+                Expression::AlwaysWithin(OrderedFloat(time), Box::new(exp1), Box::new(exp2))
+            },
+        )(input)?;
+        log::debug!("END < parse_always_within {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_hold_during(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_hold_during {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::HoldDuring, tuple((number, number, Expression::parse_expression))),
+                Token::CloseParen,
+            ),
+            |(start, end, expression)| {
+                Expression::HoldDuring(OrderedFloat(start), OrderedFloat(end), Box::new(expression))
+            },
+        )(input)?;
+        log::debug!("END < parse_hold_during {:?}", output.span());
+        Ok((output, expression))
+    }
+
+    fn parse_hold_after(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        log::debug!("BEGIN > parse_hold_after {:?}", input.span());
+        let (output, expression) = map(
+            delimited(
+                Token::OpenParen,
+                preceded(Token::HoldAfter, pair(number, Expression::parse_expression)),
+                Token::CloseParen,
+            ),
+            |(time, expression)| Expression::HoldAfter(OrderedFloat(time), Box::new(expression)),
+        )(input)?;
+        log::debug!("END < parse_hold_after {:?}", output.span());
+        Ok((output, expression))
+    }
+}
+
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pddl())
+    }
 }
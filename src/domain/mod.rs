@@ -1,7 +1,40 @@
+/// Implement [`PartialEq`], [`Eq`], [`PartialOrd`], [`Ord`] and [`Hash`](std::hash::Hash) for a struct by comparing the listed fields only, in order.
+macro_rules! impl_cmp_by_fields {
+    ($type:ty { $($field:ident),+ $(,)? }) => {
+        impl PartialEq for $type {
+            fn eq(&self, other: &Self) -> bool {
+                $(self.$field == other.$field)&&+
+            }
+        }
+
+        impl Eq for $type {}
+
+        impl PartialOrd for $type {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $type {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                std::cmp::Ordering::Equal$(.then_with(|| self.$field.cmp(&other.$field)))+
+            }
+        }
+
+        impl std::hash::Hash for $type {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                $(std::hash::Hash::hash(&self.$field, state);)+
+            }
+        }
+    };
+}
+
 /// This module contains the definition of an actionable item. An actionable item can be an action or a durative action.
 pub mod action;
 /// This module contains the definition of a constant. A constant is a value that is not changed by the actions.
 pub mod constant;
+/// This module contains the definition of a derived predicate. A derived predicate is a predicate whose truth value is defined by a formula.
+pub mod derived;
 /// This module contains the definition of a domain. A domain is a set of actions, predicates, constants, and types.
 pub mod domain;
 /// This module contains the definition of a durative action. A durative action is a function that takes a set of parameters and returns a set of effects. It also has a duration.
@@ -24,3 +57,7 @@ pub mod typed_predicate;
 pub mod typedef;
 /// This module contains the definition of a type.
 pub mod typing;
+/// This module contains the definition of a validation error. A validation error is a domain that parses but is not consistent with its own declarations.
+pub mod validate;
+/// This module contains the definition of a domain visitor. A domain visitor is called for every element of a domain.
+pub mod visitor;
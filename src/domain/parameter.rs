@@ -33,14 +33,24 @@ impl Parameter {
         Ok((output, params))
     }
 
+    /// Get the parameter as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if the parameter is a variable (i.e. it starts with a question mark).
+    pub fn is_variable(&self) -> bool {
+        self.0.starts_with('?')
+    }
+
     /// Convert the parameter to PDDL.
     pub fn to_pddl(&self) -> String {
         self.0.clone()
     }
 }
 
-impl ToString for Parameter {
-    fn to_string(&self) -> String {
-        self.0.clone()
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
@@ -1,5 +1,7 @@
+use std::collections::HashSet;
+
 use nom::branch::alt;
-use nom::combinator::{map, opt};
+use nom::combinator::map;
 use nom::multi::many0;
 use nom::sequence::{delimited, preceded};
 use nom::IResult;
@@ -146,35 +148,86 @@ impl Requirement {
         ))(input)
     }
 
-    const fn is_supported(&self) -> bool {
-        matches!(
-            self,
-            Requirement::Strips | Requirement::Typing | Requirement::DurativeActions | Requirement::NumericFluents
-        )
+    /// Returns the requirement itself followed by the requirements it implies, transitively. For example, `:adl` implies `:strips`, `:typing`, `:negative-preconditions`, `:disjunctive-preconditions`, `:equality`, `:quantified-preconditions` (and so `:existential-preconditions` and `:universal-preconditions`) and `:conditional-effects`.
+    pub fn implied(&self) -> Vec<Requirement> {
+        let direct = match self {
+            Requirement::Adl => vec![
+                Requirement::Strips,
+                Requirement::Typing,
+                Requirement::NegativePreconditions,
+                Requirement::DisjunctivePreconditions,
+                Requirement::Equality,
+                Requirement::QuantifiedPreconditions,
+                Requirement::ConditionalEffects,
+            ],
+            Requirement::Ucpop => vec![Requirement::Adl, Requirement::DomainAxioms, Requirement::SafetyConstraints],
+            Requirement::QuantifiedPreconditions => {
+                vec![Requirement::ExistentialPreconditions, Requirement::UniversalPreconditions]
+            },
+            Requirement::Fluents => vec![Requirement::NumericFluents],
+            Requirement::DurativeInequalities | Requirement::TimedInitialLiterals => {
+                vec![Requirement::DurativeActions]
+            },
+            _ => vec![],
+        };
+        let mut implied = vec![self.clone()];
+        for requirement in direct.iter().flat_map(Requirement::implied) {
+            if !implied.contains(&requirement) {
+                implied.push(requirement);
+            }
+        }
+        implied
+    }
+
+    /// The requirements whose features are supported by the parser, including the shorthands (e.g. `:adl`) whose implied requirements are all supported. These are the requirements allowed by default (see [`crate::options::ParseOptions::allowed_requirements`]).
+    pub fn supported() -> HashSet<Requirement> {
+        [
+            Requirement::Strips,
+            Requirement::Typing,
+            Requirement::Equality,
+            Requirement::NegativePreconditions,
+            Requirement::DisjunctivePreconditions,
+            Requirement::ExistentialPreconditions,
+            Requirement::UniversalPreconditions,
+            Requirement::QuantifiedPreconditions,
+            Requirement::ConditionalEffects,
+            Requirement::Adl,
+            Requirement::DurativeActions,
+            Requirement::DurativeInequalities,
+            Requirement::Fluents,
+            Requirement::NumericFluents,
+            Requirement::ActionCosts,
+            Requirement::DerivedPredicates,
+            Requirement::TimedInitialLiterals,
+            Requirement::Preferences,
+            Requirement::Constraints,
+        ]
+        .into_iter()
+        .collect()
     }
 
     /// Parse the requirements from a token stream.
+    ///
+    /// Consecutive `(:requirements ...)` blocks (as emitted by some tools) are merged: the requirements are returned in order of appearance, without duplicates.
+    ///
+    /// Any requirement can be declared: the allowed requirements (see [`crate::options::ParseOptions::allowed_requirements`]) are checked where the syntax of their features is parsed.
     pub fn parse_requirements(input: TokenStream) -> IResult<TokenStream, Vec<Requirement>, ParserError> {
         log::debug!("BEGIN > parse_requirements {:?}", input.span());
-        let (output, requirements) = opt(delimited(
+        let (output, blocks) = many0(delimited(
             Token::OpenParen,
             preceded(Token::Requirements, many0(Requirement::parse_requirement)),
             Token::CloseParen,
         ))(input)?;
-
-        if let Some(requirements) = &requirements {
-            for requirement in requirements {
-                if !requirement.is_supported() {
-                    return Err(nom::Err::Error(ParserError::UnsupportedRequirement(
-                        requirement.clone(),
-                    )));
-                }
+        let mut requirements = Vec::new();
+        for requirement in blocks.into_iter().flatten() {
+            if !requirements.contains(&requirement) {
+                requirements.push(requirement);
             }
         }
 
         log::debug!("Requirements: {requirements:?}");
         log::debug!("END < parse_requirements {:?}", output.span());
-        Ok((output, requirements.unwrap_or_default()))
+        Ok((output, requirements))
     }
 
     /// Convert the requirement to the PDDL requirement string.
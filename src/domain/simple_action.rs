@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use nom::branch::alt;
 use nom::combinator::{map, opt};
 use nom::sequence::{delimited, preceded, tuple};
 use nom::IResult;
@@ -7,10 +11,13 @@ use super::expression::Expression;
 use super::typed_parameter::TypedParameter;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
+use crate::pretty::PrettyOptions;
 use crate::tokens::id;
 
 /// An action with typed parameters.
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// [`PartialEq`], [`Ord`] and [`Hash`](std::hash::Hash) are implemented manually to ignore the [`SimpleAction::span`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SimpleAction {
     /// The name of the action.
     pub name: String,
@@ -19,20 +26,35 @@ pub struct SimpleAction {
     pub parameters: Vec<TypedParameter>,
     /// The precondition of the action.
     pub precondition: Option<Expression>,
-    /// The effect of the action.
+    /// The effect of the action. An action without `:effect` has an empty effect, i.e. `(and)`.
     pub effect: Expression,
+    /// The comment documenting the action, i.e. the comment lines immediately preceding its definition. It is only captured when parsing with [`crate::options::ParseOptions::preserve_comments`].
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// The source range of the action, from its opening to its closing parenthesis. It is only recorded when parsing with [`crate::options::ParseOptions::record_spans`], and it is ignored by comparisons and hashing.
+    #[serde(skip)]
+    pub span: Option<Range<usize>>,
 }
 
+impl_cmp_by_fields!(SimpleAction { name, parameters, precondition, effect, doc });
+
 impl SimpleAction {
-    /// Parse a list of actions from a token stream.
+    /// Parse an action from a token stream. The action can be introduced by either `:action` or `:operator`.
     pub fn parse(input: TokenStream) -> IResult<TokenStream, SimpleAction, ParserError> {
         log::debug!("BEGIN > parse_action {:?}", input.span());
         log::debug!("Parsing action: {:?}", input.peek_n(10));
-        let (output, action) = map(
+        let doc = if input.options().preserve_comments {
+            input.leading_comment()
+        }
+        else {
+            None
+        };
+        let start = input.clone();
+        let (output, mut action) = map(
             delimited(
                 Token::OpenParen,
                 preceded(
-                    Token::Action,
+                    alt((Token::Action, Token::Operator)),
                     tuple((
                         id,
                         preceded(
@@ -44,7 +66,7 @@ impl SimpleAction {
                             ),
                         ),
                         opt(preceded(Token::Precondition, Expression::parse_expression)),
-                        preceded(Token::Effect, Expression::parse_expression),
+                        opt(preceded(Token::Effect, Expression::parse_expression)),
                     )),
                 ),
                 Token::CloseParen,
@@ -53,16 +75,58 @@ impl SimpleAction {
                 name,
                 parameters,
                 precondition,
-                effect,
+                effect: effect.unwrap_or_else(|| Expression::And(vec![])),
+                doc: doc.clone(),
+                span: None,
             },
         )(input)?;
+        if output.options().record_spans {
+            action.span = Some(output.span_since(&start));
+        }
         log::debug!("END < parse_action {:?}", output.span());
         Ok((output, action))
     }
 
-    /// Convert the action to PDDL.
+    /// Instantiate the action with the objects bound to its variables, e.g. `{"?arm": "robot1"}`.
+    ///
+    /// Every bound variable is replaced by its object in the parameters (which keep their type), the precondition and the effect, including the variables used as terms (e.g. `(= ?x ?y)` becomes `(= a b)`). Unbound variables, and variables bound by an inner quantifier, are left intact, so the action can be grounded partially.
+    ///
+    /// The grounded precondition and effect can be written and parsed back as expressions. The grounded action itself is meant to be inspected, not written back: its `:parameters` hold object names instead of variables, so the output of [`SimpleAction::to_pddl`] is not a valid action and cannot be parsed again.
+    #[must_use]
+    pub fn ground(&self, bindings: &HashMap<String, String>) -> SimpleAction {
+        SimpleAction {
+            name: self.name.clone(),
+            parameters: self
+                .parameters
+                .iter()
+                .map(|parameter| TypedParameter {
+                    name: bindings.get(&parameter.name).unwrap_or(&parameter.name).clone(),
+                    type_: parameter.type_.clone(),
+                })
+                .collect(),
+            precondition: self
+                .precondition
+                .as_ref()
+                .map(|precondition| precondition.substitute(bindings)),
+            effect: self.effect.substitute(bindings),
+            doc: self.doc.clone(),
+            span: None,
+        }
+    }
+
+    /// Convert the action to PDDL. The documentation comment, if any, is emitted on the lines preceding the definition.
     pub fn to_pddl(&self) -> String {
-        let mut pddl = String::new();
+        self.to_pddl_with_options(&PrettyOptions::default())
+    }
+
+    /// Convert the action to PDDL, using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
+        let mut pddl = self
+            .doc
+            .iter()
+            .flat_map(|doc| doc.lines())
+            .map(|line| format!("; {line}\n"))
+            .collect::<String>();
 
         // Action name
         pddl.push_str(&format!("(:action {}\n", self.name));
@@ -79,13 +143,19 @@ impl SimpleAction {
 
         // Precondition
         if let Some(precondition) = &self.precondition {
-            pddl.push_str(&format!(":precondition {}\n", precondition.to_pddl()));
+            pddl.push_str(&format!(":precondition {}\n", options.expression(precondition)));
         }
 
         // Effect
-        pddl.push_str(&format!(":effect \n{}\n", self.effect.to_pddl()));
+        pddl.push_str(&format!(":effect \n{}\n", options.expression(&self.effect)));
 
         pddl.push(')');
         pddl
     }
 }
+
+impl std::fmt::Display for SimpleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pddl())
+    }
+}
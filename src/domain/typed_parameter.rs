@@ -4,10 +4,11 @@ use nom::sequence::{pair, preceded};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
+use super::requirement::Requirement;
 use super::typing::Type;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::var;
+use crate::tokens::{requires, var};
 
 /// A parameter with a type.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -24,7 +25,10 @@ impl TypedParameter {
     /// Parse a list of typed parameters from a token stream.
     pub fn parse_typed_parameters(input: TokenStream) -> IResult<TokenStream, Vec<TypedParameter>, ParserError> {
         log::debug!("BEGIN > parse_typed_parameters {:?}", input.span());
-        let (output, params) = many0(pair(many1(var), opt(preceded(Token::Dash, Type::parse_type))))(input)?;
+        let (output, params) = many0(pair(
+            many1(var),
+            opt(preceded(pair(Token::Dash, requires(&[Requirement::Typing])), Type::parse_type)),
+        ))(input)?;
         let params = params
             .into_iter()
             .flat_map(|(names, type_)| {
@@ -1,36 +1,99 @@
+use std::ops::Range;
+
 use nom::combinator::opt;
-use nom::multi::many0;
+use nom::multi::{many0, many1};
 use nom::sequence::{delimited, pair, preceded};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
 use crate::domain::typed_parameter::TypedParameter;
+use crate::domain::typing::Type;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::tokens::predicate_name;
 
-/// A predicate with typed parameters.
+/// The typed signature of a predicate, i.e. its name and the name and type of each of its parameters. See [`crate::domain::domain::Domain::predicate_schema`].
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PredicateSchema {
+    /// The name of the predicate.
+    pub name: String,
+    /// The name and type of each parameter, in order.
+    pub params: Vec<(String, Type)>,
+}
+
+impl From<&TypedPredicate> for PredicateSchema {
+    fn from(predicate: &TypedPredicate) -> Self {
+        PredicateSchema {
+            name: predicate.name.clone(),
+            params: predicate
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name.clone(), parameter.type_.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A predicate with typed parameters.
+///
+/// [`PartialEq`], [`Ord`] and [`Hash`](std::hash::Hash) are implemented manually to ignore the [`TypedPredicate::span`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TypedPredicate {
     /// The name of the predicate.
     pub name: String,
     /// The parameters of the predicate.
     #[serde(default)]
     pub parameters: Vec<TypedParameter>,
+    /// The comment documenting the predicate, i.e. the comment lines immediately preceding its declaration. It is only captured when parsing with [`crate::options::ParseOptions::preserve_comments`].
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// The return type of a function, as declared with `- type` after the function (e.g. `(fuel ?t - truck) - number`). It is always `None` for predicates, and for functions declared without a return type.
+    #[serde(default)]
+    pub return_type: Option<Type>,
+    /// The source range of the declaration, from its opening to its closing parenthesis. It is only recorded when parsing with [`crate::options::ParseOptions::record_spans`], and it is ignored by comparisons and hashing.
+    #[serde(skip)]
+    pub span: Option<Range<usize>>,
 }
 
+impl_cmp_by_fields!(TypedPredicate { name, parameters, doc, return_type });
+
 impl TypedPredicate {
-    /// Parse a list of functions from a token stream.
+    pub(crate) fn parse_typed_predicate(input: TokenStream) -> IResult<TokenStream, TypedPredicate, ParserError> {
+        let doc = if input.options().preserve_comments {
+            input.leading_comment()
+        }
+        else {
+            None
+        };
+        let start = input.clone();
+        let (output, (name, parameters)) = delimited(
+            Token::OpenParen,
+            pair(predicate_name, TypedParameter::parse_typed_parameters),
+            Token::CloseParen,
+        )(input)?;
+        let span = output.options().record_spans.then(|| output.span_since(&start));
+        Ok((
+            output,
+            TypedPredicate {
+                name,
+                parameters,
+                doc,
+                return_type: None,
+                span,
+            },
+        ))
+    }
+
+    /// Parse a list of functions from a token stream. Consecutive functions can share a return type, as in `(f1 ?x) (f2 ?y) - number`.
     pub fn parse_functions(input: TokenStream) -> IResult<TokenStream, Vec<TypedPredicate>, ParserError> {
         log::debug!("BEGIN > parse_functions {:?}", input.span());
         let (output, functions) = opt(delimited(
             Token::OpenParen,
             preceded(
                 Token::Functions,
-                many0(delimited(
-                    Token::OpenParen,
-                    pair(id, TypedParameter::parse_typed_parameters),
-                    Token::CloseParen,
+                many0(pair(
+                    many1(TypedPredicate::parse_typed_predicate),
+                    opt(preceded(Token::Dash, Type::parse_type)),
                 )),
             ),
             Token::CloseParen,
@@ -38,7 +101,12 @@ impl TypedPredicate {
         let functions = functions
             .unwrap_or_default()
             .into_iter()
-            .map(|(name, parameters)| TypedPredicate { name, parameters })
+            .flat_map(|(functions, return_type)| {
+                functions.into_iter().map(move |function| TypedPredicate {
+                    return_type: return_type.clone(),
+                    ..function
+                })
+            })
             .collect();
         log::debug!("END < parse_functions {:?}", output.span());
         Ok((output, functions))
@@ -51,26 +119,24 @@ impl TypedPredicate {
             Token::OpenParen,
             preceded(
                 Token::Predicates,
-                many0(delimited(
-                    Token::OpenParen,
-                    pair(id, TypedParameter::parse_typed_parameters),
-                    Token::CloseParen,
-                )),
+                many0(TypedPredicate::parse_typed_predicate),
             ),
             Token::CloseParen,
         )(input)?;
-        let predicates = predicates
-            .into_iter()
-            .map(|(name, parameters)| TypedPredicate { name, parameters })
-            .collect();
         log::debug!("END < parse_predicates {:?}", output.span());
         Ok((output, predicates))
     }
 
-    /// Convert the predicate to PDDL.
+    /// Convert the predicate to PDDL. The documentation comment, if any, is emitted on the lines preceding the declaration.
     pub fn to_pddl(&self) -> String {
+        let doc = self
+            .doc
+            .iter()
+            .flat_map(|doc| doc.lines())
+            .map(|line| format!("; {line}\n"))
+            .collect::<String>();
         format!(
-            "({} {})",
+            "{doc}({} {})",
             self.name,
             self.parameters
                 .iter()
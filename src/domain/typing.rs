@@ -7,10 +7,11 @@ use nom::sequence::{delimited, pair, preceded};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
+use super::requirement::Requirement;
 use super::typedef::TypeDef;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::tokens::{id, requires};
 
 /// A type is either a simple type or one of the list of specified types.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -35,25 +36,41 @@ impl Default for Type {
 
 impl Type {
     /// Parse a type from a token stream.
+    ///
+    /// An `either` type with a single type, such as `(either a)`, is parsed as the simple type `a`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ParserError::EmptyEither`] failure if the type is an `either` type without any type, i.e. `(either)`.
     pub fn parse_type(input: TokenStream) -> IResult<TokenStream, Type, ParserError> {
         log::debug!("BEGIN > parse_type {:?}", input.span());
         let (output, type_) = alt((
             map(id, Type::Simple),
-            map(
-                delimited(Token::OpenParen, preceded(Token::Either, many1(id)), Token::CloseParen),
-                Type::Either,
-            ),
+            delimited(Token::OpenParen, preceded(Token::Either, Type::parse_either), Token::CloseParen),
         ))(input)?;
         log::debug!("END < parse_type {:?}", output.span());
         Ok((output, type_))
     }
 
+    fn parse_either(input: TokenStream) -> IResult<TokenStream, Type, ParserError> {
+        let span = input.span();
+        let (output, mut types) = many0(id)(input)?;
+        match types.len() {
+            0 => Err(nom::Err::Failure(ParserError::EmptyEither(span))),
+            1 => Ok((output, Type::Simple(types.remove(0)))),
+            _ => Ok((output, Type::Either(types))),
+        }
+    }
+
     /// Parse a list of types from a token stream.
     pub fn parse_types(input: TokenStream) -> IResult<TokenStream, Vec<TypeDef>, ParserError> {
         log::debug!("BEGIN > parse_types {:?}", input.span());
         let (output, types) = delimited(
             Token::OpenParen,
-            preceded(Token::Types, many0(pair(many1(id), opt(preceded(Token::Dash, id))))),
+            preceded(
+                pair(Token::Types, requires(&[Requirement::Typing])),
+                many0(pair(many1(id), opt(preceded(Token::Dash, id)))),
+            ),
             Token::CloseParen,
         )(input)?;
         let types = types
@@ -69,6 +86,14 @@ impl Type {
         Ok((output, types))
     }
 
+    /// Returns the names of the type: the name of a simple type, or the names of the types of an `either` type.
+    pub fn names(&self) -> &[String] {
+        match self {
+            Type::Simple(name) => std::slice::from_ref(name),
+            Type::Either(names) => names.as_slice(),
+        }
+    }
+
     /// Convert the type to PDDL.
     pub fn to_pddl(&self) -> String {
         match self {
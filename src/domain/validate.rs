@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::domain::typing::Type;
+
+/// A semantic error in a domain, i.e. a domain that parses but is not consistent with its own declarations, or in a problem that is not consistent with its domain. See [`crate::domain::domain::Domain::validate`] and [`crate::problem::Problem::validate_against`].
+#[derive(Error, Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationError {
+    /// An action uses an atom that is neither a declared predicate nor a declared function.
+    #[error("Action `{action}` uses undeclared predicate `{predicate}`")]
+    UndeclaredPredicate {
+        /// The name of the action.
+        action: String,
+        /// The name of the undeclared predicate.
+        predicate: String,
+    },
+
+    /// An action uses a predicate or a function with a different number of arguments than it is declared with.
+    #[error("Action `{action}` uses `{predicate}` with {found} arguments, but it is declared with {expected}")]
+    ArityMismatch {
+        /// The name of the action.
+        action: String,
+        /// The name of the predicate or function.
+        predicate: String,
+        /// The number of parameters of the declaration.
+        expected: usize,
+        /// The number of arguments used by the action.
+        found: usize,
+    },
+
+    /// An action passes an argument whose type is not a subtype of the type that the predicate or function declares for it.
+    #[error(
+        "Action `{action}` uses `{predicate}` with `{argument}` of type `{}`, but it is declared with `{}`",
+        .found.to_pddl(),
+        .expected.to_pddl()
+    )]
+    TypeMismatch {
+        /// The name of the action.
+        action: String,
+        /// The name of the predicate or function.
+        predicate: String,
+        /// The argument, i.e. a variable or a constant.
+        argument: String,
+        /// The declared type of the parameter.
+        expected: Type,
+        /// The type of the argument.
+        found: Type,
+    },
+
+    /// A problem refers to another domain than the one it is validated against.
+    #[error("Problem `{problem}` refers to domain `{found}`, but the domain is `{expected}`")]
+    DomainMismatch {
+        /// The name of the problem.
+        problem: String,
+        /// The name of the domain.
+        expected: String,
+        /// The domain referred to by the problem.
+        found: String,
+    },
+
+    /// A problem uses an atom that is neither a predicate nor a function of its domain.
+    #[error("Problem `{problem}` uses undeclared predicate `{predicate}`")]
+    ProblemUndeclaredPredicate {
+        /// The name of the problem.
+        problem: String,
+        /// The name of the undeclared predicate.
+        predicate: String,
+    },
+
+    /// A problem uses a predicate or a function with a different number of arguments than the domain declares it with.
+    #[error("Problem `{problem}` uses `{predicate}` with {found} arguments, but it is declared with {expected}")]
+    ProblemArityMismatch {
+        /// The name of the problem.
+        problem: String,
+        /// The name of the predicate or function.
+        predicate: String,
+        /// The number of parameters of the declaration.
+        expected: usize,
+        /// The number of arguments used by the problem.
+        found: usize,
+    },
+
+    /// A type is used but not declared in `:types`.
+    #[error("Undeclared type `{name}` used by {context}")]
+    UndeclaredType {
+        /// The name of the undeclared type.
+        name: String,
+        /// What uses the type, e.g. ``action `move` `` or ``constant `a` ``.
+        context: String,
+    },
+}
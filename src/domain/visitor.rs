@@ -0,0 +1,24 @@
+use super::action::Action;
+use super::expression::Expression;
+use super::typed_predicate::TypedPredicate;
+use super::typedef::TypeDef;
+
+/// A visitor over a whole [`Domain`](super::domain::Domain). See [`Domain::accept`](super::domain::Domain::accept).
+///
+/// All of the methods do nothing by default, so implementors only need to override the ones they are interested in.
+pub trait DomainVisitor {
+    /// Called for each type definition of the domain.
+    fn visit_type(&mut self, _type_def: &TypeDef) {}
+
+    /// Called for each predicate of the domain.
+    fn visit_predicate(&mut self, _predicate: &TypedPredicate) {}
+
+    /// Called for each function of the domain.
+    fn visit_function(&mut self, _function: &TypedPredicate) {}
+
+    /// Called for each action of the domain, before its expressions are visited.
+    fn visit_action(&mut self, _action: &Action) {}
+
+    /// Called for each expression of the domain (durations, preconditions and effects), including every nested sub-expression.
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
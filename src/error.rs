@@ -8,9 +8,11 @@ use crate::domain::requirement::Requirement;
 use crate::lexer::Token;
 
 /// A PDDL parser error
+///
+/// Some errors carry the byte span of their location in the parsed input. The parsers of whole files (e.g. [`crate::domain::domain::Domain::parse`]) return them wrapped in [`ParserError::Located`], so that they are displayed with their line and column.
 #[derive(Error, Debug, PartialEq, Clone, Default)]
 pub enum ParserError {
-    /// The PDDL file contains an unsupported requirement (e.g. `:fluents`). See the `Requirement` section of the [README.md](https://github.com/MrRobb/pddl-parser#pddl-requirements-supported) for a list of supported requirements.
+    /// The PDDL file uses the syntax of a requirement that is not allowed (e.g. `(when ...)` for `:conditional-effects`, see [`crate::options::ParseOptions::allowed_requirements`]). See the `Requirement` section of the [README.md](https://github.com/MrRobb/pddl-parser#pddl-requirements-supported) for a list of supported requirements.
     #[error("Unsupported PDDL Requirement: {0:?}")]
     UnsupportedRequirement(Requirement),
 
@@ -46,12 +48,94 @@ pub enum ParserError {
     #[error("Expected end of input")]
     ExpectedEndOfInput,
 
+    /// A fact was expected to be ground, but it still contains a variable (e.g. a quantified initial fact whose variable could not be bound).
+    #[error("Unbound variable: {0}")]
+    UnboundVariable(String),
+
+    /// Two initial states assign different values to the same fluent (e.g. `(= (fuel truck) 10)` and `(= (fuel truck) 20)`).
+    #[error("Conflicting assignments to fluent: {0}")]
+    ConflictingAssignment(String),
+
+    /// An `either` type does not contain any type, i.e. `(either)`. The span is the one of the `either` keyword.
+    #[error("Empty `either` type")]
+    EmptyEither(Range<usize>),
+
+    /// The goal of a problem is wrapped in a redundant pair of parentheses, e.g. `(:goal ((and ...)))`. It is only accepted in lenient mode (see [`crate::options::ParseOptions::lenient`]). The span is the one of the `:goal` keyword.
+    #[error("Redundant parentheses around the goal")]
+    RedundantParentheses(Range<usize>),
+
+    /// An action was looked up by name, but the domain does not declare it (see [`crate::domain::domain::Domain::duplicate_action`]).
+    #[error("Unknown action: {0}")]
+    UnknownAction(String),
+
+    /// An action cannot be added because the domain already declares an action with the same name.
+    #[error("Duplicate action: {0}")]
+    DuplicateAction(String),
+
+    /// An error along with the 1-based line and column of its location in the parsed input (see [`ParserError::location`]), e.g. `Expected token: CloseParen (line 4, column 9)`. Use [`ParserError::inner`] to get the error itself.
+    #[error("{0} (line {1}, column {2})")]
+    Located(Box<ParserError>, usize, usize),
+
     /// An unknown error. Default error variant. This should never be returned.
     #[default]
     #[error("Unknown error")]
     UnknownError,
 }
 
+impl ParserError {
+    /// Returns the 1-based `(line, column)` in `source` of the token that caused the error, if the error carries a span (see [`ParserError::ExpectedToken`], [`ParserError::EmptyEither`] and [`ParserError::RedundantParentheses`]) or is already [`ParserError::Located`].
+    ///
+    /// The span of an error is the span of the last token that was successfully parsed, so the location is the one of the next token, skipping whitespace and comments. The parser backtracks to the start of the element that failed to parse, so this is the start of that element rather than the exact mistake: for instance, a `(:predicates ...)` section missing its closing parenthesis is reported at its opening parenthesis. Columns are counted in characters. `source` must be the input that was parsed.
+    pub fn location(&self, source: &str) -> Option<(usize, usize)> {
+        let span = match self {
+            ParserError::Located(_, line, column) => return Some((*line, *column)),
+            ParserError::ExpectedToken(_, span, _)
+            | ParserError::EmptyEither(span)
+            | ParserError::RedundantParentheses(span) => span,
+            _ => return None,
+        };
+        let mut offset = span.end;
+        loop {
+            let rest = source.get(offset..)?;
+            let trimmed = rest.trim_start();
+            offset += rest.len() - trimmed.len();
+            if !trimmed.starts_with(';') {
+                break;
+            }
+            offset += trimmed.find(['\n', '\r']).unwrap_or(trimmed.len());
+        }
+        let (mut line, mut column) = (1, 1);
+        let mut chars = source[..offset].chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\n' || (c == '\r' && chars.peek() != Some(&'\n')) {
+                line += 1;
+                column = 1;
+            }
+            else if c != '\r' {
+                column += 1;
+            }
+        }
+        Some((line, column))
+    }
+
+    /// Wrap the error in [`ParserError::Located`] if its location in `source` is known (see [`ParserError::location`]). `source` must be the input that was parsed.
+    #[must_use]
+    pub fn with_location(self, source: &str) -> Self {
+        match (&self, self.location(source)) {
+            (ParserError::Located(..), _) | (_, None) => self,
+            (_, Some((line, column))) => ParserError::Located(Box::new(self), line, column),
+        }
+    }
+
+    /// Returns the error without its location, i.e. the error wrapped in [`ParserError::Located`], or the error itself.
+    pub fn inner(&self) -> &ParserError {
+        match self {
+            ParserError::Located(error, _, _) => error,
+            _ => self,
+        }
+    }
+}
+
 impl<I: ToString> ParseError<I> for ParserError {
     fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
         ParserError::ParseError(kind, input.to_string())
@@ -91,6 +175,13 @@ impl From<nom::Err<ParserError>> for ParserError {
                 ParserError::LexerError => ParserError::LexerError,
                 ParserError::UnknownError => ParserError::UnknownError,
                 ParserError::ExpectedEndOfInput => ParserError::ExpectedEndOfInput,
+                ParserError::UnboundVariable(variable) => ParserError::UnboundVariable(variable),
+                ParserError::ConflictingAssignment(fluent) => ParserError::ConflictingAssignment(fluent),
+                ParserError::EmptyEither(span) => ParserError::EmptyEither(span),
+                ParserError::RedundantParentheses(span) => ParserError::RedundantParentheses(span),
+                ParserError::UnknownAction(name) => ParserError::UnknownAction(name),
+                ParserError::DuplicateAction(name) => ParserError::DuplicateAction(name),
+                ParserError::Located(error, line, column) => ParserError::Located(error, line, column),
             },
         }
     }
@@ -1,10 +1,12 @@
 use std::ops::Range;
+use std::rc::Rc;
 
 use derive_more::Display;
 use logos::Logos;
 use nom::InputLength;
 
 use crate::error::ParserError;
+use crate::options::ParseOptions;
 
 /// All of the possible tokens in a PDDL file
 #[derive(Logos, Debug, Display, Clone, PartialEq)]
@@ -24,11 +26,11 @@ pub enum Token {
     CloseBracket,
 
     /// An open parenthesis `(`
-    #[regex(r"\([ \t\n\f]*")]
+    #[regex(r"\([ \t\n\f\r]*")]
     OpenParen,
 
     /// A close parenthesis `)`
-    #[regex(r"\)[ \t\n\f]*")]
+    #[regex(r"\)[ \t\n\f\r]*")]
     CloseParen,
 
     // PDDL Keywords
@@ -76,6 +78,10 @@ pub enum Token {
     #[token(":action", ignore(ascii_case))]
     Action,
 
+    /// The `:operator` keyword (an alias of `:action` used by older dialects)
+    #[token(":operator", ignore(ascii_case))]
+    Operator,
+
     /// The `:durative-action` keyword
     #[token(":durative-action", ignore(ascii_case))]
     DurativeAction,
@@ -108,6 +114,34 @@ pub enum Token {
     #[token(":goal", ignore(ascii_case))]
     Goal,
 
+    /// The `:horizon` keyword
+    #[token(":horizon", ignore(ascii_case))]
+    Horizon,
+
+    /// The `:bounds` keyword
+    #[token(":bounds", ignore(ascii_case))]
+    Bounds,
+
+    /// The `:metric` keyword
+    #[token(":metric", ignore(ascii_case))]
+    Metric,
+
+    /// The `:private` keyword (MA-PDDL)
+    #[token(":private", ignore(ascii_case))]
+    Private,
+
+    /// The `:agent` keyword (MA-PDDL)
+    #[token(":agent", ignore(ascii_case))]
+    Agent,
+
+    /// The `:derived` keyword
+    #[token(":derived", ignore(ascii_case))]
+    Derived,
+
+    /// The `:description` keyword
+    #[token(":description", ignore(ascii_case))]
+    Description,
+
     /// The `and` keyword
     #[token("and", ignore(ascii_case))]
     And,
@@ -116,6 +150,10 @@ pub enum Token {
     #[token("not", ignore(ascii_case))]
     Not,
 
+    /// The `or` keyword
+    #[token("or", ignore(ascii_case))]
+    Or,
+
     /// The `either` keyword
     #[token("either", ignore(ascii_case))]
     Either,
@@ -144,6 +182,14 @@ pub enum Token {
     #[token("forall", ignore(ascii_case))]
     Forall,
 
+    /// The `when` keyword
+    #[token("when", ignore(ascii_case))]
+    When,
+
+    /// The `exists` keyword
+    #[token("exists", ignore(ascii_case))]
+    Exists,
+
     /// The `at` keyword
     #[token("at", ignore(ascii_case))]
     At,
@@ -164,12 +210,60 @@ pub enum Token {
     #[token("end", ignore(ascii_case))]
     End,
 
-    /// A number (positive or negative, e.g. `1` or `-1`)
+    /// The `preference` keyword
+    #[token("preference", ignore(ascii_case))]
+    Preference,
+
+    /// The `always` keyword
+    #[token("always", ignore(ascii_case))]
+    Always,
+
+    /// The `sometime` keyword
+    #[token("sometime", ignore(ascii_case))]
+    Sometime,
+
+    /// The `within` keyword
+    #[token("within", ignore(ascii_case))]
+    Within,
+
+    /// The `at-most-once` keyword
+    #[token("at-most-once", ignore(ascii_case))]
+    AtMostOnce,
+
+    /// The `sometime-after` keyword
+    #[token("sometime-after", ignore(ascii_case))]
+    SometimeAfter,
+
+    /// The `sometime-before` keyword
+    #[token("sometime-before", ignore(ascii_case))]
+    SometimeBefore,
+
+    /// The `always-within` keyword
+    #[token("always-within", ignore(ascii_case))]
+    AlwaysWithin,
+
+    /// The `hold-during` keyword
+    #[token("hold-during", ignore(ascii_case))]
+    HoldDuring,
+
+    /// The `hold-after` keyword
+    #[token("hold-after", ignore(ascii_case))]
+    HoldAfter,
+
+    /// The `minimize` keyword
+    #[token("minimize", ignore(ascii_case))]
+    Minimize,
+
+    /// The `maximize` keyword
+    #[token("maximize", ignore(ascii_case))]
+    Maximize,
+
+    /// A number (positive or negative, e.g. `1` or `-1`). A `-` immediately followed by a digit is the sign of the number, so `(- 3 -1)` subtracts `-1` from `3`. Otherwise, it is a [`Token::Dash`].
     #[regex(r"-?[0-9]+", |lex| lex.slice().parse())]
     Integer(i64),
 
-    /// A floating point number (e.g. `1.0`)
-    #[regex(r"[0-9]+\.[0-9]+", |lex| lex.slice().parse())]
+    /// A floating point number (positive or negative, e.g. `1.0` or `-1.0`)
+    #[regex(r"-?[0-9]+\.[0-9]+", |lex| lex.slice().parse())]
     Float(f64),
 
     // Math operators
@@ -189,6 +283,22 @@ pub enum Token {
     #[token("=")]
     Equal,
 
+    /// The `<` operator
+    #[token("<")]
+    Less,
+
+    /// The `>` operator
+    #[token(">")]
+    Greater,
+
+    /// The `<=` operator
+    #[token("<=")]
+    LessEqual,
+
+    /// The `>=` operator
+    #[token(">=")]
+    GreaterEqual,
+
     /// The `:strips` requirement (PDDL 1)
     #[token(":strips", ignore(ascii_case))]
     Strips,
@@ -333,14 +443,19 @@ pub enum Token {
     #[regex(r"\?[a-zA-Z][a-zA-Z0-9_\-]*", |lex| lex.slice().to_string())]
     Var(String),
 
+    // String literal
+    /// A string literal enclosed in double quotes (e.g. `"A blocksworld domain"`). The escape sequences `\"`, `\\`, `\n` and `\t` are supported.
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| unescape(&lex.slice()[1..lex.slice().len() - 1]))]
+    StringLit(String),
+
     // Dash
-    /// A dash (`-`) character that can represent a minus sign or a hyphen
+    /// A dash (`-`) character that can represent a minus sign (the subtraction operator) or a hyphen (the type separator). A dash immediately followed by a digit is lexed as the sign of a number instead (see [`Token::Integer`]), so the subtraction operator must be followed by a space or a parenthesis, as in `(- (fuel ?x) 1)`.
     #[token("-")]
     Dash,
 
     // Comments
-    /// A comment (a semicolon followed by any characters). The comment is ignored.
-    #[regex(r";.*", logos::skip)]
+    /// A comment (a semicolon followed by any characters until the end of the line, which may be `\n` or `\r`). The comment is ignored.
+    #[regex(r";[^\n\r]*", logos::skip)]
     Comment,
 
     // Packages
@@ -349,28 +464,90 @@ pub enum Token {
     Package,
 }
 
+/// Resolve the escape sequences of the content of a string literal.
+fn unescape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some(c) => output.push(c),
+                None => output.push('\\'),
+            }
+        }
+        else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+/// Escape a string so that it can be written as a PDDL string literal (including the surrounding double quotes).
+pub fn escape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len() + 2);
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
 /// A stream of tokens. This is a wrapper around a [`logos::Lexer`]. It implements [`Clone`], so it can be cloned and used to peek ahead. It also implements [`Iterator`], so it can be used to iterate over the tokens.
 #[derive(Debug)]
 pub struct TokenStream<'a> {
     lexer: logos::Lexer<'a, Token>,
+    options: Rc<ParseOptions>,
 }
 
 impl Clone for TokenStream<'_> {
     fn clone(&self) -> Self {
         Self {
             lexer: self.lexer.clone(),
+            options: Rc::clone(&self.options),
         }
     }
 }
 
+thread_local! {
+    /// The default options, shared by the token streams created without options so that they are only built once per thread.
+    static DEFAULT_OPTIONS: Rc<ParseOptions> = Rc::default();
+}
+
 impl<'a> TokenStream<'a> {
     /// Creates a new token stream from the given input string. The input string is not copied, so it must outlive the token stream.
     pub fn new(input: &'a str) -> Self {
         Self {
             lexer: Token::lexer(input),
+            options: DEFAULT_OPTIONS.with(Rc::clone),
         }
     }
 
+    /// Sets the options used by the parsers that consume this token stream.
+    #[must_use]
+    pub fn with_options(mut self, options: &ParseOptions) -> Self {
+        self.options = Rc::new(options.clone());
+        self
+    }
+
+    /// Returns the options used by the parsers that consume this token stream.
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Returns the whole input string, including the part that has already been consumed.
+    pub fn source(&self) -> &'a str {
+        self.lexer.source()
+    }
+
     /// Returns the remaining input string.
     pub fn len(&self) -> usize {
         self.lexer.source().len() - self.lexer.span().end
@@ -411,6 +588,79 @@ impl<'a> TokenStream<'a> {
         self
     }
 
+    /// Resynchronizes the stream on a balanced parenthesis boundary, e.g. after an error.
+    ///
+    /// The next parenthesized group (including any nested groups) is skipped, as well as any stray token after it, so that the next token is the next `(` at the same depth, or the `)` that closes the enclosing group. If the next token already closes the enclosing group, the stream is not advanced. If a group is never closed, the stream is left empty.
+    #[must_use]
+    pub fn resync(mut self) -> Self {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                None => return self,
+                Some((Ok(Token::CloseParen), _)) if depth == 0 => return self,
+                Some((Ok(Token::OpenParen), _)) => depth += 1,
+                Some((Ok(Token::CloseParen), _)) => depth -= 1,
+                _ => {},
+            }
+            self = self.advance();
+            if depth == 0 && matches!(self.peek(), Some((Ok(Token::OpenParen), _))) {
+                return self;
+            }
+        }
+    }
+
+    /// Returns the comment block immediately preceding the next token, if any.
+    ///
+    /// The block is made of the consecutive comment lines right above the next token. A blank line ends the block, and a comment on the same line as the previous token is not part of it. The leading semicolons and one space are removed from each line, and the lines are joined with `\n`.
+    pub fn leading_comment(&self) -> Option<String> {
+        let source = self.lexer.source();
+        // The span of a token may include the whitespace that follows it, so only its trimmed text is skipped.
+        let start = self.lexer.span().start + self.lexer.slice().trim_end().len();
+        let end = self.lexer.clone().spanned().next().map_or(source.len(), |(_, span)| span.start);
+        let gap = source[start..end].replace("\r\n", "\n").replace('\r', "\n");
+        let mut lines = gap.split('\n').collect::<Vec<_>>();
+        lines.pop();
+        if start > 0 && !lines.is_empty() {
+            lines.remove(0);
+        }
+        let mut comment = lines
+            .iter()
+            .rev()
+            .map(|line| line.trim())
+            .take_while(|line| line.starts_with(';'))
+            .map(|line| {
+                let line = line.trim_start_matches(';');
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect::<Vec<_>>();
+        if comment.is_empty() {
+            return None;
+        }
+        comment.reverse();
+        Some(comment.join("\n"))
+    }
+
+    /// Skips the next token in the stream if it is `token`.
+    ///
+    /// This is a lighter alternative to parsing with the token itself (see the [`nom::Parser`] implementation of [`Token`]), meant for performance-sensitive inner loops: the error only contains the expected token and the current span, without the upcoming tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParserError::ExpectedToken`] if the next token is not `token`.
+    pub fn expect(self, token: &Token) -> Result<Self, ParserError> {
+        match self.lexer.clone().next() {
+            Some(Ok(t)) if t == *token => Ok(self.advance()),
+            _ => Err(ParserError::ExpectedToken(token.clone(), self.span(), None)),
+        }
+    }
+
+    /// Returns the source range from the next token of `start` to the end of the last token consumed by this stream, excluding the whitespace that follows it. This stream must be `start` after parsing an element, so the range is the one of the element.
+    pub fn span_since(&self, start: &TokenStream) -> Range<usize> {
+        let begin = start.lexer.clone().spanned().next().map_or(start.lexer.span().end, |(_, span)| span.start);
+        let end = self.lexer.span().start + self.lexer.slice().trim_end().len();
+        begin..end.max(begin)
+    }
+
     /// Returns the span of the current token.
     pub fn span(&self) -> Range<usize> {
         self.lexer.span()
@@ -430,9 +680,9 @@ impl<'a> nom::Parser<TokenStream<'a>, &'a str, ParserError> for Token {
     }
 }
 
-impl ToString for TokenStream<'_> {
-    fn to_string(&self) -> String {
-        self.lexer.source().to_string()
+impl std::fmt::Display for TokenStream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lexer.source())
     }
 }
 
@@ -17,14 +17,22 @@
 
 //! # PDDL Parser
 
+/// The cache module contains helpers to avoid re-parsing the same PDDL source.
+pub mod cache;
 /// The domain module contains the types used to represent a PDDL domain.
 pub mod domain;
 /// The error module contains the error types used by the library.
 pub mod error;
 /// The lexer module contains the lexer used to tokenize a PDDL file.
 pub mod lexer;
+/// The options module contains the options used to control the parsing.
+pub mod options;
 /// The plan module contains the types used to represent a PDDL plan.
 pub mod plan;
+/// The prelude module re-exports the most commonly used types, so that `use pddl_parser::prelude::*;` is enough to parse and inspect PDDL files.
+pub mod prelude;
+/// The pretty module contains the options used to control the PDDL output.
+pub mod pretty;
 /// The problem module contains the types used to represent a PDDL problem.
 pub mod problem;
 /// The tokens module contains the functions used to parse tokens.
@@ -32,19 +40,33 @@ pub mod tokens;
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::collections::{BTreeSet, HashMap};
+
+    use crate::cache::parse_cached_with;
+    use crate::domain::action::EffectSummary;
+    use crate::domain::constant::Constant;
     use crate::domain::domain::Domain;
     use crate::domain::durative_action::DurativeAction;
-    use crate::domain::expression::{BinaryOp, DurationInstant, Expression};
+    use crate::domain::expression::{BinaryOp, DurationInstant, Expression, OrderedFloat};
+    use crate::domain::parameter::Parameter;
     use crate::domain::requirement::Requirement;
     use crate::domain::typed_parameter::TypedParameter;
-    use crate::domain::typed_predicate::TypedPredicate;
+    use crate::domain::typed_predicate::{PredicateSchema, TypedPredicate};
     use crate::domain::typedef::TypeDef;
+    use crate::domain::typing::Type;
+    use crate::domain::validate::ValidationError;
+    use crate::domain::visitor::DomainVisitor;
     use crate::domain::{self};
+    use crate::error::ParserError;
+    use crate::lexer::{Token, TokenStream};
+    use crate::options::ParseOptions;
     use crate::plan;
     use crate::plan::action::Action;
     use crate::plan::plan::Plan;
     use crate::plan::simple_action::SimpleAction;
-    use crate::problem::{Object, Problem};
+    use crate::pretty::PrettyOptions;
+    use crate::problem::{Metric, Object, Problem};
 
     #[test]
     fn test_domain_to_pddl() {
@@ -102,6 +124,7 @@ mod tests {
             Problem {
                 name: "letseat-simple".into(),
                 domain: "letseat".into(),
+                description: None,
                 objects: vec![
                     Object {
                         name: "arm".into(),
@@ -141,7 +164,11 @@ mod tests {
                 goal: Expression::Atom {
                     name: "on".into(),
                     parameters: vec!["cupcake".into(), "plate".into()]
-                }
+                },
+                constraints: None,
+                metrics: vec![],
+                horizon: None,
+                skipped_init: vec![],
             }
         );
     }
@@ -156,6 +183,7 @@ mod tests {
             Domain::parse(domain_example.into()).expect("Failed to parse domain"),
             Domain {
                 name: "letseat".into(),
+                description: None,
                 requirements: vec![Requirement::Typing],
                 types: vec![
                     TypeDef {
@@ -193,6 +221,9 @@ mod tests {
                                 type_: "location".into(),
                             },
                         ],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "holding".into(),
@@ -206,10 +237,16 @@ mod tests {
                                 type_: "locatable".into(),
                             },
                         ],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "arm-empty".into(),
                         parameters: vec![],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "path".into(),
@@ -223,6 +260,9 @@ mod tests {
                                 type_: "location".into(),
                             },
                         ],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                 ],
                 functions: vec![],
@@ -270,7 +310,9 @@ mod tests {
                                 name: "arm-empty".into(),
                                 parameters: vec![],
                             })),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Simple(domain::simple_action::SimpleAction {
                         name: "drop".into(),
@@ -311,7 +353,9 @@ mod tests {
                                 name: "holding".into(),
                                 parameters: vec!["?arm".into(), "?cupcake".into(),],
                             })),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Simple(domain::simple_action::SimpleAction {
                         name: "move".into(),
@@ -348,9 +392,14 @@ mod tests {
                                 name: "on".into(),
                                 parameters: vec!["?arm".into(), "?to".into(),],
                             },
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     })
                 ],
+                derived: vec![],
+                constraints: None,
+                extensions: BTreeSet::new(),
             }
         );
     }
@@ -365,6 +414,7 @@ mod tests {
             Domain::parse(durative_actions_domain.into()).expect("Failed to parse domain"),
             Domain {
                 name: "collaborative-cloth-piling".into(),
+                description: None,
                 requirements: vec![
                     Requirement::Strips,
                     Requirement::Typing,
@@ -430,6 +480,9 @@ mod tests {
                                 type_: "agent".into(),
                             },
                         ],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "graspable".into(),
@@ -437,6 +490,9 @@ mod tests {
                             name: "?o".into(),
                             type_: "object".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "free-to-manipulate".into(),
@@ -444,6 +500,9 @@ mod tests {
                             name: "?a".into(),
                             type_: "agent".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "on-pile".into(),
@@ -457,6 +516,9 @@ mod tests {
                                 type_: "pile".into(),
                             },
                         ],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "piled".into(),
@@ -464,6 +526,9 @@ mod tests {
                             name: "?g".into(),
                             type_: "garment".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "supported".into(),
@@ -471,6 +536,9 @@ mod tests {
                             name: "?g".into(),
                             type_: "garment".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "lifted".into(),
@@ -478,6 +546,9 @@ mod tests {
                             name: "?g".into(),
                             type_: "garment".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "folded".into(),
@@ -485,6 +556,9 @@ mod tests {
                             name: "?g".into(),
                             type_: "garment".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "unfolded".into(),
@@ -492,6 +566,9 @@ mod tests {
                             name: "?g".into(),
                             type_: "garment".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                 ],
                 constants: vec![],
@@ -502,6 +579,9 @@ mod tests {
                             name: "?a".into(),
                             type_: "agent".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "current-number-of-garments-on-pile".into(),
@@ -509,6 +589,9 @@ mod tests {
                             name: "?p".into(),
                             type_: "pile".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                     TypedPredicate {
                         name: "target-number-of-garments-on-pile".into(),
@@ -516,6 +599,9 @@ mod tests {
                             name: "?p".into(),
                             type_: "pile".into(),
                         },],
+                        doc: None,
+                        return_type: None,
+                        span: None,
                     },
                 ],
                 actions: vec![
@@ -533,10 +619,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Atom {
                                 name: "grasp-time".into(),
                                 parameters: vec!["?a".into()],
@@ -587,7 +670,9 @@ mod tests {
                                     parameters: vec!["?g".into(), "?a".into()],
                                 })
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Durative(DurativeAction {
                         name: "grasp-unfolded-garment".into(),
@@ -603,10 +688,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Number(100))
                         ),
                         condition: Some(Expression::And(vec![
@@ -654,7 +736,9 @@ mod tests {
                                     parameters: vec!["?g".into(), "?h".into()],
                                 })
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Durative(DurativeAction {
                         name: "lift".into(),
@@ -670,10 +754,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Number(100))
                         ),
                         condition: Some(Expression::And(vec![
@@ -707,7 +788,9 @@ mod tests {
                                     parameters: vec!["?g".into()],
                                 })
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Durative(DurativeAction {
                         name: "pile-garment".into(),
@@ -731,10 +814,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Atom {
                                 name: "grasp-time".into(),
                                 parameters: vec!["?a".into()],
@@ -809,7 +889,9 @@ mod tests {
                                     Box::new(Expression::Number(1))
                                 ))
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Durative(DurativeAction {
                         name: "fold-garment".into(),
@@ -825,10 +907,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Number(100))
                         ),
                         condition: Some(Expression::And(vec![
@@ -904,7 +983,9 @@ mod tests {
                                     parameters: vec!["?g".into()],
                                 })
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
                     domain::action::Action::Durative(DurativeAction {
                         name: "grasp-pile-of-garments".into(),
@@ -920,10 +1001,7 @@ mod tests {
                         ],
                         duration: Expression::BinaryOp(
                             BinaryOp::Equal,
-                            Box::new(Expression::Atom {
-                                name: "?duration".into(),
-                                parameters: vec![]
-                            }),
+                            Box::new(Expression::Term("?duration".into())),
                             Box::new(Expression::Number(100))
                         ),
                         condition: Some(Expression::And(vec![
@@ -978,9 +1056,14 @@ mod tests {
                                     parameters: vec!["?p".into(), "?h".into()],
                                 })
                             ),
-                        ])
+                        ]),
+                        doc: None,
+                        span: None,
                     }),
-                ]
+                ],
+                derived: vec![],
+                constraints: None,
+                extensions: BTreeSet::new(),
             }
         );
     }
@@ -1102,4 +1185,3124 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_parse_cached() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let parses = Cell::new(0);
+        let parse = |source: &str| {
+            parses.set(parses.get() + 1);
+            Domain::parse(source.into())
+        };
+        let mut cache = HashMap::new();
+        let first = parse_cached_with(domain_example, &mut cache, parse).expect("Failed to parse domain");
+        let second = parse_cached_with(domain_example, &mut cache, parse).expect("Failed to parse domain");
+        assert_eq!(first, second);
+        assert_eq!(parses.get(), 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_problem_constraints_preference() {
+        let problem_example = r"
+        (define (problem deliver)
+            (:domain logistics)
+            (:objects pkg - package)
+            (:init (at-depot pkg))
+            (:goal (delivered pkg))
+            (:constraints (preference p1 (sometime (delivered pkg))))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.constraints,
+            Some(Expression::Preference(
+                Some("p1".into()),
+                Box::new(Expression::Sometime(Box::new(Expression::Atom {
+                    name: "delivered".into(),
+                    parameters: vec!["pkg".into()],
+                })))
+            ))
+        );
+        let reproblem = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to parse problem again");
+        assert_eq!(problem, reproblem);
+    }
+
+    #[test]
+    fn test_object_constructors() {
+        let expected = Object {
+            name: "arm".into(),
+            type_: "robot".into(),
+        };
+        assert_eq!(Object::new("arm", "robot"), expected);
+        assert_eq!(Object::from(("arm", "robot")), expected);
+        let object: Object = ("arm", "robot").into();
+        assert_eq!(object, expected);
+    }
+
+    #[test]
+    fn test_domain_cr_line_endings() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let cr_domain_example = domain_example.replace("\r\n", "\r").replace('\n', "\r");
+        let cr_domain = Domain::parse(cr_domain_example.as_str().into()).expect("Failed to parse CR-only domain");
+        assert_eq!(domain, cr_domain);
+        let crlf_domain_example = domain_example.replace("\r\n", "\n").replace('\n', "\r\n");
+        let crlf_domain =
+            Domain::parse(crlf_domain_example.as_str().into()).expect("Failed to parse CRLF domain");
+        assert_eq!(domain, crlf_domain);
+    }
+
+    #[test]
+    fn test_domain_validate_effects() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.validate_effects(), Ok(()));
+
+        let durative_actions_domain = include_str!("../tests/durative-actions-domain.pddl");
+        let domain = Domain::parse(durative_actions_domain.into()).expect("Failed to parse domain");
+        assert_eq!(domain.validate_effects(), Ok(()));
+
+        let inconsistent_domain = r"
+        (define (domain inconsistent)
+            (:predicates (p ?x) (q ?x))
+            (:action flip
+                :parameters (?x)
+                :effect (and (p ?x) (q ?x) (not (p ?x)))
+            )
+        )";
+        let domain = Domain::parse(inconsistent_domain.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.validate_effects(),
+            Err(vec!["Action `flip` both adds and deletes (p ?x)".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_trivial_expressions() {
+        let domain_example = r"
+        (define (domain trivial)
+            (:requirements :disjunctive-preconditions)
+            (:predicates (p))
+            (:action always
+                :parameters ()
+                :precondition (and)
+                :effect (p)
+            )
+            (:action never
+                :parameters ()
+                :precondition (or)
+                :effect (p)
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let always = domain.actions[0].precondition().expect("Missing precondition");
+        let never = domain.actions[1].precondition().expect("Missing precondition");
+        assert_eq!(always, Expression::And(vec![]));
+        assert_eq!(never, Expression::Or(vec![]));
+        assert!(always.is_trivially_true());
+        assert!(!always.is_trivially_false());
+        assert!(never.is_trivially_false());
+        assert!(!never.is_trivially_true());
+        assert_eq!(always.to_pddl(), "(and)");
+        assert_eq!(never.to_pddl(), "(or)");
+        let redomain = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to parse domain again");
+        assert_eq!(domain, redomain);
+
+        let problem_example = r"
+        (define (problem trivial)
+            (:domain trivial)
+            (:objects)
+            (:init)
+            (:goal (and))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert!(problem.goal.is_trivially_true());
+        let reproblem = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to parse problem again");
+        assert_eq!(problem, reproblem);
+    }
+
+    #[test]
+    fn test_plan_actions_named() {
+        let durative_plan = include_str!("../tests/durative-plan.txt");
+        let plan = Plan::parse(durative_plan.into()).expect("Failed to parse plan");
+        assert_eq!(plan.actions_named("lift").count(), 5);
+        assert!(plan.actions_named("lift").all(|action| action.name() == "lift"));
+        assert_eq!(plan.actions_named("LIFT").count(), 5);
+        assert_eq!(plan.actions_named("unknown").count(), 0);
+    }
+
+    #[test]
+    fn test_plan_append() {
+        let first = Plan::parse("(pick-up arm cupcake table)".into()).expect("Failed to parse plan");
+        let second =
+            Plan::parse("(move arm table plate)\n(drop arm cupcake plate)".into()).expect("Failed to parse plan");
+        let mut appended = first.clone();
+        appended.append(second.clone());
+        assert_eq!(appended, first + second);
+        assert_eq!(appended, Plan::parse(include_str!("../tests/plan.txt").into()).expect("Failed to parse plan"));
+
+        let mut temporal = Plan::parse("0.000: (lift box) [10.000]".into()).expect("Failed to parse plan");
+        let subplan =
+            Plan::parse("0.000: (drop box) [5.000]\n2.500: (push box) [1.000]".into()).expect("Failed to parse plan");
+        temporal.append_shifted(subplan, 10.0);
+        let timestamps = temporal
+            .actions()
+            .filter_map(|action| match action {
+                Action::Durative(action) => Some(action.timestamp),
+                Action::Simple(_) => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(timestamps, vec![0.0, 10.0, 12.5]);
+        assert_eq!(
+            temporal.actions().map(Action::name).collect::<Vec<_>>(),
+            vec!["lift", "drop", "push"]
+        );
+    }
+
+    #[test]
+    fn test_problem_numeric_goal() {
+        let problem_example = r"
+        (define (problem score)
+            (:domain game)
+            (:objects)
+            (:init (= (score) 0))
+            (:goal (and (finished) (= (score) 100)))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.goal,
+            Expression::And(vec![
+                Expression::Atom {
+                    name: "finished".into(),
+                    parameters: vec![],
+                },
+                Expression::BinaryOp(
+                    BinaryOp::Equal,
+                    Box::new(Expression::Atom {
+                        name: "score".into(),
+                        parameters: vec![],
+                    }),
+                    Box::new(Expression::Number(100)),
+                ),
+            ])
+        );
+        assert!(problem.has_numeric_goal());
+        let reproblem = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to parse problem again");
+        assert_eq!(problem, reproblem);
+
+        let problem_example = include_str!("../tests/problem.pddl");
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert!(!problem.has_numeric_goal());
+
+        let problem_example = r"
+        (define (problem distinct)
+            (:domain blocks)
+            (:objects a b)
+            (:init)
+            (:goal (exists (?x ?y) (not (= ?x ?y))))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert!(!problem.has_numeric_goal());
+    }
+
+    #[test]
+    fn test_pretty_sort_constants() {
+        let domain_example = r"
+        (define (domain sorting)
+            (:requirements :typing)
+            (:types robot location)
+            (:constants
+                zeta alpha - robot
+                kitchen - location
+            )
+            (:predicates (at-loc ?r - robot ?l - location))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let constant_names = |pddl: &str| {
+            let redomain = Domain::parse(pddl.into()).expect("Failed to parse domain again");
+            redomain
+                .constants
+                .iter()
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let unsorted = domain.to_pddl_with_options(&PrettyOptions::default());
+        assert_eq!(unsorted, domain.to_pddl());
+        assert_eq!(constant_names(&unsorted), vec!["zeta", "alpha", "kitchen"]);
+
+        let options = PrettyOptions {
+            sort_constants: true,
+            ..PrettyOptions::default()
+        };
+        let sorted = domain.to_pddl_with_options(&options);
+        assert_eq!(constant_names(&sorted), vec!["kitchen", "alpha", "zeta"]);
+
+        let mut redomain = Domain::parse(sorted.as_str().into()).expect("Failed to parse domain again");
+        redomain.constants.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut domain = domain;
+        domain.constants.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(domain, redomain);
+    }
+
+    #[test]
+    fn test_constant_to_pddl() {
+        let constant = Constant {
+            name: "kitchen".into(),
+            type_: "location".into(),
+        };
+        assert_eq!(constant.to_pddl(), "kitchen - location");
+
+        let domain_example = r"
+        (define (domain rooms)
+            (:requirements :typing)
+            (:types location)
+            (:constants kitchen hall - location)
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert!(domain.to_pddl().contains("(:constants \nkitchen - location\nhall - location\n)"));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+
+        let parenthesized =
+            domain_example.replace("(:constants kitchen hall - location)", "(:constants (kitchen - location))");
+        assert!(Domain::parse(parenthesized.as_str().into()).is_err());
+    }
+
+    #[test]
+    fn test_pretty_sort_objects() {
+        let problem_example = include_str!("../tests/problem.pddl");
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        let options = PrettyOptions {
+            sort_objects: true,
+            ..PrettyOptions::default()
+        };
+        let reproblem = Problem::parse(problem.to_pddl_with_options(&options).as_str().into())
+            .expect("Failed to parse problem again");
+        assert_eq!(
+            reproblem.objects.iter().map(|o| o.name.as_str()).collect::<Vec<_>>(),
+            vec!["cupcake", "plate", "table", "arm"]
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let json = domain.to_json().expect("Failed to serialize domain");
+        assert_eq!(Domain::from_json(&json).expect("Failed to deserialize domain"), domain);
+
+        let problem = Problem::parse(include_str!("../tests/problem.pddl").into()).expect("Failed to parse problem");
+        let json = problem.to_json().expect("Failed to serialize problem");
+        assert_eq!(Problem::from_json(&json).expect("Failed to deserialize problem"), problem);
+
+        let plan = Plan::parse(include_str!("../tests/durative-plan.txt").into()).expect("Failed to parse plan");
+        let json = plan.to_json().expect("Failed to serialize plan");
+        assert_eq!(Plan::from_json(&json).expect("Failed to deserialize plan"), plan);
+
+        assert!(Domain::from_json("{\"name\": \"incomplete\"}").is_err());
+    }
+
+    #[test]
+    fn test_predicate_schema() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let schema = domain.predicate_schema();
+        assert_eq!(schema.len(), domain.predicates.len());
+        assert_eq!(schema[0], PredicateSchema {
+            name: "on".into(),
+            params: vec![("?obj".into(), "locatable".into()), ("?loc".into(), "location".into())],
+        });
+        assert!(schema[2].params.is_empty());
+
+        let json = serde_json::to_string(&schema).expect("Failed to serialize schema");
+        assert!(json.contains(
+            r#"{"name":"on","params":[["?obj",{"Simple":"locatable"}],["?loc",{"Simple":"location"}]]}"#
+        ));
+        let deserialized: Vec<PredicateSchema> = serde_json::from_str(&json).expect("Failed to deserialize schema");
+        assert_eq!(deserialized, schema);
+    }
+
+    #[test]
+    fn test_pretty_indent() {
+        let (_, expression) = Expression::parse_expression(
+            "(and (on ?arm ?loc) (not (free ?arm)) (forall (?b - box) (when (on ?b ?loc) (not (free ?b)))))".into(),
+        )
+        .expect("Failed to parse expression");
+        assert_eq!(
+            expression.to_pddl_pretty(),
+            "(and\n  (on ?arm ?loc)\n  (not (free ?arm))\n  (forall (?b - box)\n    (when\n      (on ?b ?loc)\n      \
+             (not (free ?b)))))"
+        );
+        let (_, atom) = Expression::parse_expression("(not (free ?arm))".into()).expect("Failed to parse expression");
+        assert_eq!(atom.to_pddl_pretty(), atom.to_pddl());
+
+        let options = PrettyOptions {
+            indent: true,
+            ..PrettyOptions::default()
+        };
+        for domain_example in [
+            include_str!("../tests/domain.pddl"),
+            include_str!("../tests/durative-actions-domain.pddl"),
+        ] {
+            let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+            let pretty = domain.to_pddl_with_options(&options);
+            assert_ne!(pretty, domain.to_pddl());
+            assert_eq!(Domain::parse(pretty.as_str().into()), Ok(domain));
+        }
+        let problem = Problem::parse(include_str!("../tests/problem.pddl").into()).expect("Failed to parse problem");
+        let pretty = problem.to_pddl_with_options(&options);
+        assert_eq!(Problem::parse(pretty.as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_problem_expand_quantified_init() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let problem_example = r"
+        (define (problem letseat-quantified)
+            (:domain letseat)
+            (:objects
+                arm - robot
+                table plate - location
+            )
+            (:init
+                (arm-empty)
+                (forall (?l - location) (on arm ?l))
+            )
+            (:goal (arm-empty))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.expand_quantified_init(&domain),
+            Ok(vec![
+                Expression::Atom {
+                    name: "arm-empty".into(),
+                    parameters: vec![],
+                },
+                Expression::Atom {
+                    name: "on".into(),
+                    parameters: vec!["arm".into(), "table".into()],
+                },
+                Expression::Atom {
+                    name: "on".into(),
+                    parameters: vec!["arm".into(), "plate".into()],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_plan_parse_soln() {
+        let soln = "\
+(pick-up arm cupcake table) ; cost = 1
+(move arm table plate) ; cost = 2
+(drop arm cupcake plate) ; cost = 1
+; Cost: 4
+";
+        let (plan, cost) = Plan::parse_soln(soln).expect("Failed to parse plan");
+        assert_eq!(
+            plan,
+            Plan::parse(include_str!("../tests/plan.txt").into()).expect("Failed to parse plan")
+        );
+        assert_eq!(cost, Some(4.0));
+
+        let soln = "0.000: (lift towel-01 robot-01)  [100.000]\n; Makespan: 100.000\n";
+        let (plan, cost) = Plan::parse_soln(soln).expect("Failed to parse plan");
+        assert_eq!(plan.actions().count(), 1);
+        assert_eq!(cost, Some(100.0));
+
+        let (_, cost) = Plan::parse_soln(include_str!("../tests/plan.txt")).expect("Failed to parse plan");
+        assert_eq!(cost, None);
+
+        // Only the trailing comment block is read, and values that are not numbers are skipped
+        let soln = "\
+; Cost: 7
+(pick-up arm cupcake table)
+; Cost: 8
+(move arm table plate)
+
+; Cost: unknown
+; Makespan: 3
+";
+        let (plan, cost) = Plan::parse_soln(soln).expect("Failed to parse plan");
+        assert_eq!(plan.actions().count(), 2);
+        assert_eq!(cost, Some(3.0));
+        let (_, cost) = Plan::parse_soln(&soln.replace("; Makespan: 3\n", "")).expect("Failed to parse plan");
+        assert_eq!(cost, None);
+    }
+
+    #[test]
+    fn test_plan_trailing_statistics() {
+        let expected = Plan::parse(include_str!("../tests/plan.txt").into()).expect("Failed to parse plan");
+        let plan = "\
+(pick-up arm cupcake table)
+(move arm table plate)
+(drop arm cupcake plate)
+; Time 0.05
+; Expanded 123
+;; Search time: 0.01s (plan found)";
+        assert_eq!(Plan::parse(plan.into()), Ok(expected.clone()));
+        assert_eq!(Plan::parse(plan.replace('\n', "\r\n").as_str().into()), Ok(expected.clone()));
+        assert_eq!(Plan::parse(format!("{plan}\n\n;\n").as_str().into()), Ok(expected));
+    }
+
+    #[test]
+    fn test_plan_equivalent() {
+        let plan = "\
+0.000: (lift towel-01 robot-01) [5.000]
+0.000: (wait robot-02) [1.000]
+5.001: (fold towel-01 robot-01) [2]";
+        let swapped = "\
+0.0005: (wait robot-02) [1]
+0: (lift towel-01 robot-01) [5]
+5.001: (fold towel-01 robot-01) [2.0001]";
+        let plan = Plan::parse(plan.into()).expect("Failed to parse plan");
+        let swapped = Plan::parse(swapped.into()).expect("Failed to parse plan");
+        assert_ne!(plan, swapped);
+        assert!(plan.equivalent(&swapped, 0.001));
+        assert!(swapped.equivalent(&plan, 0.001));
+        assert!(!plan.equivalent(&swapped, 0.0));
+
+        let reordered = plan.clone().into_iter().rev().collect::<Plan>();
+        assert!(plan.equivalent(&reordered, 0.001));
+        let delayed = "\
+5.003: (fold towel-01 robot-01) [2]
+0.000: (wait robot-02) [1.000]
+0.000: (lift towel-01 robot-01) [5.000]";
+        let delayed = Plan::parse(delayed.into()).expect("Failed to parse plan");
+        assert!(!plan.equivalent(&delayed, 0.001));
+        let shorter = plan.clone().into_iter().take(2).collect::<Plan>();
+        assert!(!plan.equivalent(&shorter, 0.001));
+
+        let chained = Plan::parse("0: (a) [1]\n0.0008: (b) [1]\n0.0016: (c) [1]".into()).expect("Failed to parse plan");
+        let shuffled = "0.0008: (b) [1]\n0: (a) [1]\n0.0016: (c) [1]";
+        let shuffled = Plan::parse(shuffled.into()).expect("Failed to parse plan");
+        assert!(chained.equivalent(&shuffled, 0.001));
+        assert!(shuffled.equivalent(&chained, 0.001));
+
+        let sequential = Plan::parse(include_str!("../tests/plan.txt").into()).expect("Failed to parse plan");
+        assert!(sequential.equivalent(&sequential, 0.0));
+        assert!(!sequential.equivalent(&sequential.clone().into_iter().rev().collect(), 0.0));
+    }
+
+    #[test]
+    fn test_expression_collect_numbers() {
+        let domain_example = r"
+        (define (domain fuel)
+            (:requirements :numeric-fluents)
+            (:predicates (located ?v ?l))
+            (:functions (fuel ?v) (money))
+            (:action refuel
+                :parameters (?v)
+                :precondition (= (fuel ?v) 20)
+                :effect (and (increase (fuel ?v) 10) (decrease (money) 3))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let action = &domain.actions[0];
+        assert_eq!(action.effect().collect_numbers(), vec![10.0, 3.0]);
+        assert_eq!(
+            action.precondition().expect("Missing precondition").collect_numbers(),
+            vec![20.0]
+        );
+    }
+
+    #[test]
+    fn test_domain_operator_keyword() {
+        let domain_example = r"
+        (define (domain legacy)
+            (:predicates (p ?x) (q ?x))
+            (:OPERATOR swap
+                :parameters (?x)
+                :precondition (p ?x)
+                :effect (and (not (p ?x)) (q ?x))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert!(matches!(&domain.actions[0], domain::action::Action::Simple(action) if action.name == "swap"));
+        let pddl = domain.to_pddl();
+        assert!(pddl.contains("(:action swap"));
+        assert!(!pddl.contains(":operator"));
+        let redomain = Domain::parse(pddl.as_str().into()).expect("Failed to parse domain again");
+        assert_eq!(domain, redomain);
+    }
+
+    #[test]
+    fn test_domain_type_usage() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let usage = domain.type_usage();
+        assert_eq!(usage.get("location"), Some(&7));
+        assert_eq!(usage.get("locatable"), Some(&5));
+        assert_eq!(usage.get("bot"), Some(&3));
+        assert_eq!(usage.get("robot"), None);
+    }
+
+    #[test]
+    fn test_increase_by_variable() {
+        let domain_example = r"
+        (define (domain counter)
+            (:requirements :numeric-fluents)
+            (:predicates (active ?p))
+            (:functions (count ?p))
+            (:action add
+                :parameters (?p ?n)
+                :effect (increase (count ?p) ?n)
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.actions[0].effect(),
+            Expression::Increase(
+                Box::new(Expression::Atom {
+                    name: "count".into(),
+                    parameters: vec!["?p".into()],
+                }),
+                Box::new(Expression::Term("?n".into())),
+            )
+        );
+        assert_eq!(domain.actions[0].effect().to_pddl(), "(increase (count ?p) ?n)");
+        let redomain = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to parse domain again");
+        assert_eq!(domain, redomain);
+    }
+
+    #[test]
+    fn test_problem_merge_init() {
+        let fragment = |init: &str| {
+            let problem =
+                format!("(define (problem fragment) (:domain trucks) (:objects) (:init {init}) (:goal (and)))");
+            Problem::parse(problem.as_str().into()).expect("Failed to parse problem")
+        };
+        let mut problem = fragment("(road a b) (= (fuel truck) 10)");
+        problem
+            .merge_init(&fragment("(road a b) (road b c) (= (fuel truck) 10) (= (load truck) 0)"))
+            .expect("Failed to merge init");
+        assert_eq!(
+            problem.init.iter().map(Expression::to_pddl).collect::<Vec<_>>(),
+            vec![
+                "(road a b)",
+                "(= (fuel truck) 10)",
+                "(road b c)",
+                "(= (load truck) 0)"
+            ]
+        );
+
+        problem
+            .merge_init(&fragment("(= (fuel truck) 10.0) (= (load truck) 0.0)"))
+            .expect("Failed to merge numerically equal assignments");
+        assert_eq!(problem.init.len(), 4);
+
+        let before = problem.clone();
+        assert_eq!(
+            problem.merge_init(&fragment("(road c d) (= (fuel truck) 20)")),
+            Err(ParserError::ConflictingAssignment("(fuel truck)".into()))
+        );
+        assert_eq!(problem, before);
+    }
+
+    #[test]
+    fn test_domain_empty_predicates() {
+        let domain_example = r"
+        (define (domain empty)
+            (:requirements :strips)
+            (:predicates)
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert!(domain.predicates.is_empty());
+        let pddl = domain.to_pddl();
+        assert!(!pddl.contains(":predicates"));
+        let redomain = Domain::parse(pddl.as_str().into()).expect("Failed to parse domain again");
+        assert_eq!(domain, redomain);
+    }
+
+    #[test]
+    fn test_domain_visitor() {
+        #[derive(Default)]
+        struct Counter {
+            actions: usize,
+            atoms: usize,
+        }
+
+        impl DomainVisitor for Counter {
+            fn visit_action(&mut self, _action: &domain::action::Action) {
+                self.actions += 1;
+            }
+
+            fn visit_expression(&mut self, expression: &Expression) {
+                if matches!(expression, Expression::Atom { .. }) {
+                    self.atoms += 1;
+                }
+            }
+        }
+
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let mut counter = Counter::default();
+        domain.accept(&mut counter);
+        assert_eq!(counter.actions, 3);
+        assert_eq!(counter.atoms, 15);
+    }
+
+    #[test]
+    fn test_description() {
+        let domain_example = r#"
+        (define (domain blocksworld)
+            (:description "A \"blocksworld\" domain")
+            (:requirements :strips)
+            (:predicates (clear ?x))
+        )"#;
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.description.as_deref(), Some("A \"blocksworld\" domain"));
+        let redomain = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to parse domain again");
+        assert_eq!(domain, redomain);
+
+        let problem_example = r#"
+        (define (problem blocks-1)
+            (:domain blocksworld)
+            (:description "Three blocks; one \\ tower")
+            (:objects a b c)
+            (:init (clear a))
+            (:goal (clear c))
+        )"#;
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(problem.description.as_deref(), Some("Three blocks; one \\ tower"));
+        let reproblem = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to parse problem again");
+        assert_eq!(problem, reproblem);
+    }
+
+    #[test]
+    fn test_domain_is_well_typed() {
+        let domain_example = include_str!("../tests/domain.pddl");
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.is_well_typed(), Ok(()));
+
+        let durative_actions_domain = include_str!("../tests/durative-actions-domain.pddl");
+        let domain = Domain::parse(durative_actions_domain.into()).expect("Failed to parse domain");
+        assert_eq!(domain.is_well_typed(), Ok(()));
+
+        let broken_domain = r"
+        (define (domain broken)
+            (:requirements :typing)
+            (:types block)
+            (:predicates (clear ?x - block) (on ?x - block ?y - table))
+            (:action stack
+                :parameters (?x - block ?y - block)
+                :precondition (and (clear ?x) (holding ?x))
+                :effect (and (on ?x) (clear ?y) (not (clear ?y)))
+            )
+        )";
+        let domain = Domain::parse(broken_domain.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.is_well_typed(),
+            Err(vec![
+                "Undeclared type `table` used by predicate `on`".to_string(),
+                "Action `stack` uses undeclared predicate `holding`".to_string(),
+                "Action `stack` uses `on` with 1 arguments, but it is declared with 2".to_string(),
+                "Action `stack` both adds and deletes (clear ?y)".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_domain_validate() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        assert_eq!(domain.validate(), Ok(()));
+
+        let broken_domain = r"
+        (define (domain broken)
+            (:requirements :typing)
+            (:types block)
+            (:predicates (clear ?x - block))
+            (:action stack
+                :parameters (?x - block ?y - pallet)
+                :precondition (and (clear ?x) (holding ?x))
+                :effect (and (on ?x ?y) (not (clear ?y ?x)))
+            )
+        )";
+        let domain = Domain::parse(broken_domain.into()).expect("Failed to parse domain");
+        let Err(errors) = domain.validate()
+        else {
+            unreachable!("The domain should not be valid");
+        };
+        assert_eq!(errors, vec![
+            ValidationError::UndeclaredType {
+                name: "pallet".into(),
+                context: "action `stack`".into(),
+            },
+            ValidationError::UndeclaredPredicate {
+                action: "stack".into(),
+                predicate: "holding".into(),
+            },
+            ValidationError::UndeclaredPredicate {
+                action: "stack".into(),
+                predicate: "on".into(),
+            },
+            ValidationError::ArityMismatch {
+                action: "stack".into(),
+                predicate: "clear".into(),
+                expected: 1,
+                found: 2,
+            },
+        ]);
+        assert_eq!(errors[1].to_string(), "Action `stack` uses undeclared predicate `holding`");
+    }
+
+    #[test]
+    fn test_domain_validate_atom_signatures() {
+        let domain_example = include_str!("../tests/domain.pddl")
+            .replacen("(arm-empty)\n        )", "(arm-empty ?arm)\n        )", 1)
+            .replace("(path ?from ?to)", "(path ?arm ?to)");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        let Err(errors) = domain.validate()
+        else {
+            unreachable!("The domain should not be valid");
+        };
+        assert_eq!(errors, vec![
+            ValidationError::ArityMismatch {
+                action: "pick-up".into(),
+                predicate: "arm-empty".into(),
+                expected: 0,
+                found: 1,
+            },
+            ValidationError::TypeMismatch {
+                action: "move".into(),
+                predicate: "path".into(),
+                argument: "?arm".into(),
+                expected: "location".into(),
+                found: "bot".into(),
+            },
+        ]);
+        assert_eq!(
+            errors[1].to_string(),
+            "Action `move` uses `path` with `?arm` of type `bot`, but it is declared with `location`"
+        );
+
+        // `robot` is a subtype of `locatable`, and quantified variables are typed by their declaration.
+        let domain_example = include_str!("../tests/domain.pddl").replace(
+            "(arm-empty)\n        )",
+            "(forall (?r - robot ?l - locatable) (on ?r ?loc))\n\
+             (exists (?l - location) (on ?l ?loc))\n        )",
+        );
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.validate(), Err(vec![ValidationError::TypeMismatch {
+            action: "pick-up".into(),
+            predicate: "on".into(),
+            argument: "?l".into(),
+            expected: "locatable".into(),
+            found: "location".into(),
+        }]));
+    }
+
+    #[test]
+    fn test_durative_plan_integer_numbers() {
+        let durative_plan = "\
+0: (lift towel-01 robot-01) [5]
+5: (fold towel-01 robot-01) [2.5]
+-0.5: (wait robot-01) [1]
+";
+        assert_eq!(
+            Plan::parse(durative_plan.into()).expect("Failed to parse plan"),
+            Plan(vec![
+                Action::Durative(plan::durative_action::DurativeAction {
+                    name: "lift".into(),
+                    parameters: vec!["towel-01".into(), "robot-01".into()],
+                    duration: 5.0,
+                    timestamp: 0.0,
+                }),
+                Action::Durative(plan::durative_action::DurativeAction {
+                    name: "fold".into(),
+                    parameters: vec!["towel-01".into(), "robot-01".into()],
+                    duration: 2.5,
+                    timestamp: 5.0,
+                }),
+                Action::Durative(plan::durative_action::DurativeAction {
+                    name: "wait".into(),
+                    parameters: vec!["robot-01".into()],
+                    duration: 1.0,
+                    timestamp: -0.5,
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_expression_simplify() {
+        let domain_example = r"
+        (define (domain messy)
+            (:requirements :disjunctive-preconditions :numeric-fluents)
+            (:predicates (p ?x) (q ?x) (r ?x))
+            (:functions (fuel ?x))
+            (:action act
+                :parameters (?x)
+                :precondition (and
+                    (and)
+                    (not (not (p ?x)))
+                    (and (q ?x) (and (r ?x)))
+                    (or (p ?x))
+                    (= (fuel ?x) (+ 2 3))
+                    (= 1 1)
+                )
+                :effect (p ?x)
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let precondition = domain.actions[0].precondition().expect("Missing precondition");
+        let simplified = precondition.simplify();
+        assert_eq!(
+            simplified.to_pddl(),
+            "(and (p ?x) (q ?x) (r ?x) (p ?x) (= (fuel ?x) 5))"
+        );
+        assert_eq!(simplified.clone().simplify(), simplified);
+
+        let contradiction = Expression::And(vec![
+            Expression::Atom {
+                name: "p".into(),
+                parameters: vec![],
+            },
+            Expression::Not(Box::new(Expression::And(vec![]))),
+        ]);
+        assert_eq!(contradiction.simplify(), Expression::Or(vec![]));
+    }
+
+    #[test]
+    fn test_problem_horizon_and_bounds() {
+        let problem_example = r"
+        (define (problem counters)
+            (:domain counters)
+            (:objects c1 c2 - counter)
+            (:init (= (value c1) 0) (= (value c2) 0))
+            (:bounds (value - number (0 10)))
+            (:goal (= (value c1) (value c2)))
+            (:horizon 20)
+        )";
+        let options = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let problem =
+            Problem::parse_with_options(problem_example.into(), &options).expect("Failed to parse problem");
+        assert_eq!(problem.horizon, Some(20));
+        assert_eq!(problem.init.len(), 2);
+        assert!(Problem::parse(problem_example.into()).is_err());
+
+        let reparsed = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to reparse problem");
+        assert_eq!(reparsed, problem);
+    }
+
+    #[test]
+    fn test_domain_infer_requirements() {
+        let mut domain = Domain::parse(include_str!("../tests/durative-actions-domain.pddl").into())
+            .expect("Failed to parse domain");
+        let inferred = domain.infer_requirements();
+        assert!(inferred.contains(&Requirement::DurativeActions));
+        assert!(inferred.contains(&Requirement::NumericFluents));
+        assert!(inferred.contains(&Requirement::Typing));
+        assert!(!inferred.contains(&Requirement::DisjunctivePreconditions));
+
+        domain.requirements.clear();
+        for requirement in inferred.iter().chain(&inferred) {
+            domain.with_requirement(requirement.clone());
+        }
+        assert_eq!(domain.requirements, inferred);
+    }
+
+    #[test]
+    fn test_problem_metrics() {
+        let problem_example = r"
+        (define (problem deliveries)
+            (:domain deliveries)
+            (:objects truck - vehicle)
+            (:init (= (total-cost) 0) (= (fuel-used truck) 0))
+            (:goal (delivered truck))
+            (:metric minimize (total-cost))
+            (:metric maximize (fuel-used truck))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(problem.metrics.len(), 2);
+        assert!(matches!(problem.metrics[1], Metric::Maximize(_)));
+        assert_eq!(problem.metrics[1].expression().to_pddl(), "(fuel-used truck)");
+        let reparsed = Problem::parse(problem.to_pddl().as_str().into()).expect("Failed to reparse problem");
+        assert_eq!(reparsed, problem);
+
+        let single = Problem::parse(
+            r"
+            (define (problem timed)
+                (:domain deliveries)
+                (:objects)
+                (:init)
+                (:goal (delivered truck))
+                (:metric minimize total-time)
+            )"
+            .into(),
+        )
+        .expect("Failed to parse problem");
+        assert_eq!(
+            single.primary_metric(),
+            Some(&Metric::Minimize(Expression::Atom {
+                name: "total-time".into(),
+                parameters: vec![],
+            }))
+        );
+        assert_eq!(problem.primary_metric(), problem.metrics.first());
+    }
+
+    #[test]
+    fn test_plan_typed_parameters() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let plan = Plan::parse(include_str!("../tests/plan.txt").into()).expect("Failed to parse plan");
+        let typed = plan.0[0].typed_parameters(&domain).expect("Missing typed parameters");
+        assert_eq!(
+            typed,
+            vec![
+                ("arm".to_string().into(), "bot".into()),
+                ("cupcake".to_string().into(), "locatable".into()),
+                ("table".to_string().into(), "location".into()),
+            ]
+        );
+
+        let unknown = Action::Simple(SimpleAction {
+            name: "fly".into(),
+            parameters: vec![],
+        });
+        assert_eq!(unknown.typed_parameters(&domain), None);
+        let wrong_arity = Action::Simple(SimpleAction {
+            name: "pick-up".into(),
+            parameters: vec!["arm".to_string().into()],
+        });
+        assert_eq!(wrong_arity.typed_parameters(&domain), None);
+    }
+
+    #[test]
+    fn test_parse_empty_input() {
+        for input in ["", "   \n\t\r\n  ", "; only a comment\n;; and another one\n"] {
+            assert!(matches!(
+                Domain::parse(input.into()).as_ref().map_err(ParserError::inner),
+                Err(ParserError::ExpectedToken(Token::OpenParen, _, None))
+            ));
+            assert!(matches!(
+                Problem::parse(input.into()).as_ref().map_err(ParserError::inner),
+                Err(ParserError::ExpectedToken(Token::OpenParen, _, None))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_domain_ground_predicate_instances() {
+        let domain = Domain::parse(
+            r"
+            (define (domain grid)
+                (:requirements :strips :typing)
+                (:types cell robot)
+                (:predicates (adjacent ?a - cell ?b - cell) (occupies ?r - robot ?c - cell))
+            )"
+            .into(),
+        )
+        .expect("Failed to parse domain");
+        let problem = Problem::parse(
+            r"
+            (define (problem small-grid)
+                (:domain grid)
+                (:objects c1 c2 - cell r1 - robot)
+                (:init)
+                (:goal (occupies r1 c2))
+            )"
+            .into(),
+        )
+        .expect("Failed to parse problem");
+        let atoms = domain
+            .ground_predicate_instances(&problem)
+            .iter()
+            .map(Expression::to_pddl)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            atoms,
+            vec![
+                "(adjacent c1 c1)",
+                "(adjacent c1 c2)",
+                "(adjacent c2 c1)",
+                "(adjacent c2 c2)",
+                "(occupies r1 c1)",
+                "(occupies r1 c2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_durative_action_without_condition() {
+        let domain_example = r"
+        (define (domain ticking)
+            (:requirements :durative-actions)
+            (:predicates (ticked))
+            (:durative-action tick
+                :parameters ()
+                :duration (= ?duration 1)
+                :effect (at end (ticked))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.actions[0].precondition(), None);
+        assert!(!domain.actions[0].to_pddl().contains(":condition"));
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_plan_compute_cost() {
+        let domain = Domain::parse(
+            r"
+            (define (domain travel)
+                (:requirements :strips :typing :numeric-fluents)
+                (:types city)
+                (:predicates (in ?c - city))
+                (:functions (total-cost) (distance ?a - city ?b - city))
+                (:action drive
+                    :parameters (?from - city ?to - city)
+                    :precondition (in ?from)
+                    :effect (and (not (in ?from)) (in ?to) (increase (total-cost) 5))
+                )
+                (:action fly
+                    :parameters (?from - city ?to - city)
+                    :precondition (in ?from)
+                    :effect (and (not (in ?from)) (in ?to) (increase (total-cost) (distance ?from ?to)))
+                )
+                (:action rest
+                    :parameters (?c - city)
+                    :precondition (in ?c)
+                    :effect (in ?c)
+                )
+            )"
+            .into(),
+        )
+        .expect("Failed to parse domain");
+        let plan = Plan::parse("(drive a b) (rest b) (DRIVE b c)".into()).expect("Failed to parse plan");
+        assert_eq!(plan.compute_cost(&domain), Some(10.0));
+        let plan = Plan::parse("(drive a b) (fly b c)".into()).expect("Failed to parse plan");
+        assert_eq!(plan.compute_cost(&domain), None);
+        let plan = Plan::parse("(drive a b) (swim b c)".into()).expect("Failed to parse plan");
+        assert_eq!(plan.compute_cost(&domain), None);
+    }
+
+    #[test]
+    fn test_predicate_doc_comments_without_blank_lines() {
+        let domain_example = r"
+        (define (domain documented)
+            (:requirements :strips :numeric-fluents)
+            (:predicates
+                ; The robot is ready to move.
+                (ready ?r)
+                ; The robot holds the object.
+                (carrying ?r ?o)
+                (idle ?r)
+            )
+            (:functions
+                (battery ?r)
+                ; Distance travelled by the robot.
+                (odometer ?r)
+            )
+        )";
+        let options = ParseOptions {
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        let domain = Domain::parse_with_options(domain_example.into(), &options).expect("Failed to parse domain");
+        let docs = domain
+            .predicates
+            .iter()
+            .chain(&domain.functions)
+            .map(|predicate| predicate.doc.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            vec![
+                Some("The robot is ready to move."),
+                Some("The robot holds the object."),
+                None,
+                None,
+                Some("Distance travelled by the robot."),
+            ]
+        );
+
+        let reparsed =
+            Domain::parse_with_options(domain.to_pddl().as_str().into(), &options).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_predicate_doc_comments() {
+        let domain_example = r"
+        (define (domain documented)
+            (:requirements :strips :numeric-fluents)
+            (:predicates
+                ; The robot is ready to move.
+                ; It becomes false while carrying.
+                (ready ?r)
+
+                ; Not attached: separated by a blank line.
+
+                (carrying ?r ?o) ; trailing comment
+                (idle ?r)
+            )
+            (:functions
+                ;; Battery level of the robot
+                (battery ?r)
+            )
+        )";
+        let options = ParseOptions {
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        let domain = Domain::parse_with_options(domain_example.into(), &options).expect("Failed to parse domain");
+        assert_eq!(
+            domain.predicates[0].doc.as_deref(),
+            Some("The robot is ready to move.\nIt becomes false while carrying.")
+        );
+        assert_eq!(domain.predicates[1].doc, None);
+        assert_eq!(domain.predicates[2].doc, None);
+        assert_eq!(domain.functions[0].doc.as_deref(), Some("Battery level of the robot"));
+
+        let reparsed =
+            Domain::parse_with_options(domain.to_pddl().as_str().into(), &options).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+
+        let plain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert!(plain.predicates.iter().all(|predicate| predicate.doc.is_none()));
+    }
+
+    #[test]
+    fn test_action_comments_round_trip() {
+        let domain_example = "(define (domain comments)
+            (:requirements :strips :durative-actions)
+            (:predicates (at-home ?r) (away ?r))
+            ; Leave home.
+            ; Only possible while at home.
+            (:action leave
+                :parameters (?r)
+                :precondition (at-home ?r)
+                :effect (and (away ?r) (not (at-home ?r))))
+
+            (:action return
+                :parameters (?r)
+                :precondition (away ?r)
+                :effect (and (at-home ?r) (not (away ?r))))
+            ;; Travel takes a while.
+            (:durative-action travel
+                :parameters (?r)
+                :duration (= ?duration 5)
+                :condition (at start (at-home ?r))
+                :effect (at end (away ?r)))
+        )";
+        let options = ParseOptions {
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        let domain = Domain::parse_with_options(domain_example.into(), &options).expect("Failed to parse domain");
+        let docs = domain
+            .actions
+            .iter()
+            .map(|action| match action {
+                domain::action::Action::Simple(action) => action.doc.as_deref(),
+                domain::action::Action::Durative(action) => action.doc.as_deref(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            vec![
+                Some("Leave home.\nOnly possible while at home."),
+                None,
+                Some("Travel takes a while.")
+            ]
+        );
+        assert!(domain.to_pddl().contains("; Leave home.\n; Only possible while at home.\n(:action leave"));
+
+        let reparsed =
+            Domain::parse_with_options(domain.to_pddl().as_str().into(), &options).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+
+        let plain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert!(!plain.to_pddl().contains(';'));
+    }
+
+    #[test]
+    fn test_token_stream_expect() {
+        let input = TokenStream::new("(:domain letseat)");
+        let input = input.expect(&Token::OpenParen).expect("Expected an open parenthesis");
+        let input = input.expect(&Token::ProblemDomain).expect("Expected the domain keyword");
+        assert_eq!(input.peek().map(|(_, s)| s), Some("letseat"));
+        assert_eq!(
+            input.expect(&Token::CloseParen).err(),
+            Some(ParserError::ExpectedToken(Token::CloseParen, 1..8, None))
+        );
+    }
+
+    #[test]
+    fn test_problem_validate_objects() {
+        let problem = Problem::parse(
+            r"
+            (define (problem duplicated)
+                (:domain things)
+                (:objects a - t1 b c - t2 a - t2)
+                (:init)
+                (:goal (and))
+            )"
+            .into(),
+        )
+        .expect("Failed to parse problem");
+        assert_eq!(
+            problem.validate_objects(),
+            Err(vec!["Object `a` is declared 2 times (with types t1, t2)".to_string()])
+        );
+        let problem = Problem::parse(include_str!("../tests/problem.pddl").into()).expect("Failed to parse problem");
+        assert_eq!(problem.validate_objects(), Ok(()));
+    }
+
+    #[test]
+    fn test_domain_producers_and_consumers() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let names = |actions: Vec<&domain::action::Action>| {
+            actions.iter().map(|action| action.name().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(names(domain.producers_of("holding")), vec!["pick-up"]);
+        assert_eq!(names(domain.consumers_of("holding")), vec!["drop"]);
+        assert_eq!(names(domain.producers_of("on")), vec!["drop", "move"]);
+        assert_eq!(names(domain.consumers_of("path")), vec!["move"]);
+        assert!(domain.producers_of("path").is_empty());
+
+        let domain_example = r"
+        (define (domain consumers)
+            (:requirements :adl)
+            (:predicates (p ?x) (q) (r))
+            (:action some-p
+                :parameters ()
+                :precondition (exists (?y) (p ?y))
+                :effect (r))
+            (:action p-or-q
+                :parameters (?x)
+                :precondition (or (p ?x) (q))
+                :effect (r))
+            (:action not-p
+                :parameters (?x)
+                :precondition (and (q) (not (p ?x)))
+                :effect (r))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(names(domain.consumers_of("p")), vec!["some-p", "p-or-q"]);
+        assert_eq!(names(domain.consumers_of("q")), vec!["p-or-q", "not-p"]);
+        assert!(domain.consumers_of("r").is_empty());
+    }
+
+    #[test]
+    fn test_exists_precondition() {
+        let domain_example = r"
+        (define (domain existential)
+            (:requirements :strips :typing :existential-preconditions)
+            (:types loc obj)
+            (:predicates (free) (connected ?a - loc ?b - loc) (placed ?o - obj ?l - loc))
+            (:action check
+                :parameters (?o - obj)
+                :precondition (and
+                    (exists () (free))
+                    (exists (?a ?b - loc ?c - obj) (and (connected ?a ?b) (placed ?c ?a)))
+                )
+                :effect (free)
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let Some(Expression::And(conjuncts)) = domain.actions[0].precondition() else {
+            unreachable!("Expected a conjunction");
+        };
+        assert_eq!(
+            conjuncts[0],
+            Expression::Exists(
+                vec![],
+                Box::new(Expression::Atom {
+                    name: "free".into(),
+                    parameters: vec![],
+                })
+            )
+        );
+        let Expression::Exists(parameters, _) = &conjuncts[1] else {
+            unreachable!("Expected an existential precondition");
+        };
+        assert_eq!(
+            parameters.iter().map(TypedParameter::to_pddl).collect::<Vec<_>>(),
+            vec!["?a - loc", "?b - loc", "?c - obj"]
+        );
+        assert_eq!(
+            conjuncts[1].to_pddl(),
+            "(exists (?a - loc ?b - loc ?c - obj) (and (connected ?a ?b) (placed ?c ?a)))"
+        );
+        assert!(domain.infer_requirements().contains(&Requirement::ExistentialPreconditions));
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_domain_lenient_extensions() {
+        let domain_example = r"
+        (define (domain ma-logistics)
+            (:requirements :strips :typing)
+            (:types truck place)
+            (:agent truck1 - truck)
+            (:predicates (located ?t - truck ?p - place))
+            (:private (driving ?t - truck) (loaded (:nested ?t)))
+            (:action drive
+                :parameters (?t - truck ?from - place ?to - place)
+                :precondition (located ?t ?from)
+                :effect (and (not (located ?t ?from)) (located ?t ?to))
+            )
+        )";
+        let options = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let domain = Domain::parse_with_options(domain_example.into(), &options).expect("Failed to parse domain");
+        assert_eq!(
+            domain.extensions,
+            [":agent", ":private"].into_iter().map(String::from).collect::<BTreeSet<_>>()
+        );
+        assert_eq!(domain.predicates.len(), 1);
+        assert_eq!(domain.actions.len(), 1);
+        assert!(Domain::parse(domain_example.into()).is_err());
+    }
+
+    #[test]
+    fn test_expression_ground_atoms() {
+        let problem = Problem::parse(include_str!("../tests/problem.pddl").into()).expect("Failed to parse problem");
+        let facts = problem
+            .init
+            .iter()
+            .flat_map(Expression::ground_atoms)
+            .map(Expression::to_pddl)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            facts,
+            vec!["(on arm table)", "(on cupcake table)", "(arm-empty )", "(path table plate)"]
+        );
+
+        let mixed = Expression::And(vec![
+            problem.goal.clone(),
+            Expression::Atom {
+                name: "on".into(),
+                parameters: vec!["?x".into(), "table".into()],
+            },
+        ]);
+        assert_eq!(mixed.ground_atoms(), vec![&problem.goal]);
+    }
+
+    #[test]
+    fn test_when_effect() {
+        let domain_example = r"
+        (define (domain conditional)
+            (:requirements :strips :conditional-effects)
+            (:predicates (p ?x) (q ?x) (r ?x))
+            (:action act
+                :parameters (?x)
+                :precondition (p ?x)
+                :effect (and (p ?x) (when (q ?x) (r ?x)) (forall (?y) (when (q ?y) (not (q ?y)))))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let Expression::And(effects) = domain.actions[0].effect() else {
+            unreachable!("Expected a conjunction");
+        };
+        assert_eq!(
+            effects[1],
+            Expression::When(
+                Box::new(Expression::Atom {
+                    name: "q".into(),
+                    parameters: vec!["?x".into()],
+                }),
+                Box::new(Expression::Atom {
+                    name: "r".into(),
+                    parameters: vec!["?x".into()],
+                })
+            )
+        );
+        assert_eq!(effects[2].to_pddl(), "(forall (?y - object) (when (q ?y) (not (q ?y))))");
+        assert_eq!(domain.producers_of("r").len(), 1);
+        assert!(domain.infer_requirements().contains(&Requirement::ConditionalEffects));
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let mut input = TokenStream::new("<= < >= > =");
+        let mut tokens = Vec::new();
+        while let Some((token, _)) = input.peek() {
+            tokens.push(token.expect("Failed to lex token"));
+            input = input.advance();
+        }
+        assert_eq!(
+            tokens,
+            vec![Token::LessEqual, Token::Less, Token::GreaterEqual, Token::Greater, Token::Equal]
+        );
+
+        let (_, constant) = Expression::parse_expression("(and (< 1 2) (<= 2 2) (>= 2 3))".into())
+            .expect("Failed to parse comparisons");
+        assert_eq!(constant.simplify(), Expression::Or(vec![]));
+
+        for (comparison, op) in [
+            ("(< (fuel ?v) 10)", BinaryOp::LessThan),
+            ("(> (fuel ?v) 10)", BinaryOp::GreaterThan),
+            ("(<= (fuel ?v) (capacity ?v))", BinaryOp::LessThanOrEqual),
+            ("(>= ?x 3)", BinaryOp::GreaterThanOrEqual),
+        ] {
+            let (_, expression) =
+                Expression::parse_expression(comparison.into()).expect("Failed to parse comparison");
+            assert!(matches!(&expression, Expression::BinaryOp(parsed, _, _) if *parsed == op));
+            assert_eq!(expression.to_pddl(), comparison);
+        }
+
+        let (_, equality) = Expression::parse_expression("(= ?x table)".into()).expect("Failed to parse equality");
+        assert_eq!(
+            equality,
+            Expression::BinaryOp(
+                BinaryOp::Equal,
+                Box::new(Expression::Term("?x".into())),
+                Box::new(Expression::Term("table".into()))
+            )
+        );
+        assert_eq!(equality.to_pddl(), "(= ?x table)");
+    }
+
+    #[test]
+    fn test_problem_hard_goal() {
+        let problem_example = r"
+        (define (problem soft)
+            (:domain letseat)
+            (:objects cupcake - cupcake plate - location)
+            (:init)
+            (:goal (and (preference p1 (on cupcake plate)) (preference (arm-empty))))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert!(matches!(&problem.goal, Expression::And(goals) if goals.len() == 2));
+        assert_eq!(problem.hard_goal(), None);
+
+        let problem_example = problem_example.replace("(preference p1 (on cupcake plate))", "(on cupcake plate)");
+        let problem = Problem::parse(problem_example.as_str().into()).expect("Failed to parse problem");
+        assert_eq!(problem.hard_goal().map(|goal| goal.to_pddl()), Some("(on cupcake plate)".to_string()));
+
+        let problem = Problem::parse(include_str!("../tests/problem.pddl").into()).expect("Failed to parse problem");
+        assert_eq!(problem.hard_goal(), Some(problem.goal.clone()));
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let domain_example = r"
+        (define (domain refuel)
+            (:requirements :durative-actions :numeric-fluents)
+            (:predicates (ready ?v))
+            (:functions (fuel ?v))
+            (:durative-action refuel
+                :parameters (?v)
+                :duration (= ?duration 2.5)
+                :condition (at start (<= (fuel ?v) 10.75))
+                :effect (at end (increase (fuel ?v) 0.5))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let domain::action::Action::Durative(action) = &domain.actions[0] else {
+            unreachable!("Expected a durative action");
+        };
+        assert_eq!(
+            action.duration,
+            Expression::BinaryOp(
+                BinaryOp::Equal,
+                Box::new(Expression::Term("?duration".into())),
+                Box::new(Expression::Float(OrderedFloat(2.5)))
+            )
+        );
+        assert_eq!(action.effect.collect_numbers(), vec![0.5]);
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+
+        assert_eq!(Expression::Float(OrderedFloat(3.0)).to_pddl(), "3");
+        assert_eq!(Expression::Float(OrderedFloat(-0.0)).to_pddl(), "0");
+        assert_eq!(Expression::Float(OrderedFloat(10.75)).to_pddl(), "10.75");
+
+        // Integral floats are written as integers, so they are parsed back as numbers
+        let (_, expression) = Expression::parse_expression("(>= (fuel ?v) 2.0)".into()).expect("Failed to parse");
+        let pddl = expression.to_pddl();
+        let (_, reparsed) = Expression::parse_expression(pddl.as_str().into()).expect("Failed to reparse");
+        assert_eq!(pddl, "(>= (fuel ?v) 2)");
+        assert_eq!(
+            reparsed,
+            Expression::BinaryOp(
+                BinaryOp::GreaterThanOrEqual,
+                Box::new(Expression::Atom {
+                    name: "fuel".into(),
+                    parameters: vec!["?v".into()],
+                }),
+                Box::new(Expression::Number(2))
+            )
+        );
+        assert_eq!(reparsed.to_pddl(), pddl);
+
+        let mut floats = std::collections::HashSet::new();
+        floats.insert(Expression::Float(OrderedFloat(f64::NAN)));
+        assert!(floats.contains(&Expression::Float(OrderedFloat(f64::NAN))));
+        assert_ne!(Expression::Float(OrderedFloat(0.0)), Expression::Float(OrderedFloat(-0.0)));
+        assert!(Expression::Float(OrderedFloat(-0.0)) < Expression::Float(OrderedFloat(0.0)));
+    }
+
+    #[test]
+    fn test_negative_numbers_and_subtraction() {
+        let parse = |input: &str| {
+            let (output, expression) = Expression::parse_expression(input.into()).expect("Failed to parse expression");
+            assert!(output.is_empty());
+            expression
+        };
+        let fuel = || {
+            Box::new(Expression::Atom {
+                name: "fuel".into(),
+                parameters: vec!["?x".into()],
+            })
+        };
+        assert_eq!(
+            parse("(- (fuel ?x) 1)"),
+            Expression::BinaryOp(BinaryOp::Subtract, fuel(), Box::new(Expression::Number(1)))
+        );
+        assert_eq!(
+            parse("(-(fuel ?x) 1)"),
+            Expression::BinaryOp(BinaryOp::Subtract, fuel(), Box::new(Expression::Number(1)))
+        );
+        assert_eq!(
+            parse("(- 3 -1)"),
+            Expression::BinaryOp(
+                BinaryOp::Subtract,
+                Box::new(Expression::Number(3)),
+                Box::new(Expression::Number(-1))
+            )
+        );
+        assert_eq!(parse("(increase (fuel ?x) -1)"), Expression::Increase(fuel(), Box::new(Expression::Number(-1))));
+        assert_eq!(
+            parse("(decrease (fuel ?x) -2.5)"),
+            Expression::Decrease(fuel(), Box::new(Expression::Float(OrderedFloat(-2.5))))
+        );
+        assert_eq!(parse("(- 3 -1)").to_pddl(), "(- 3 -1)");
+    }
+
+    #[test]
+    fn test_durative_plan_colon_spacing() {
+        let expected = Plan(vec![Action::Durative(plan::durative_action::DurativeAction::new(
+            "act".into(),
+            vec!["a".to_string().into()],
+            5.0,
+            10.0,
+        ))]);
+        for input in [
+            "10.0: (act a) [5.0]",
+            "10.0 : (act a) [5.0]",
+            "10.0 :(act a)[5.0]",
+            "10.0:(act a)[ 5.0 ]",
+            "10\t:\n(act a) [5]",
+        ] {
+            assert_eq!(Plan::parse(input.into()).expect("Failed to parse plan"), expected);
+        }
+    }
+
+    #[test]
+    fn test_functions_return_types() {
+        let domain_example = r"
+        (define (domain logistics)
+            (:requirements :typing :numeric-fluents)
+            (:types truck location)
+            (:functions
+                (fuel ?t - truck)
+                (capacity ?t - truck) - number
+                (position ?t - truck) - location
+                (total-cost)
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let return_types = domain
+            .functions
+            .iter()
+            .map(|function| function.return_type.as_ref().map(Type::to_pddl))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            return_types,
+            vec![Some("number".into()), Some("number".into()), Some("location".into()), None]
+        );
+        let pddl = domain.to_pddl();
+        assert!(pddl.contains("(fuel ?t - truck)\n(capacity ?t - truck) - number\n(position ?t - truck) - location\n"));
+        let reparsed = Domain::parse(pddl.as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_action_costs() {
+        let domain_example = r"
+        (define (domain costly)
+            (:requirements :strips :action-costs)
+            (:predicates (done))
+            (:functions (total-cost) - number)
+            (:action finish
+                :parameters ()
+                :precondition (not (done))
+                :effect (and (done) (increase (total-cost) 5))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.requirements, vec![Requirement::Strips, Requirement::ActionCosts]);
+        assert_eq!(domain.functions[0].name, "total-cost");
+        assert!(domain.functions[0].parameters.is_empty());
+        assert_eq!(
+            domain.actions[0].effect(),
+            Expression::And(vec![
+                Expression::Atom {
+                    name: "done".into(),
+                    parameters: vec![],
+                },
+                Expression::Increase(
+                    Box::new(Expression::Atom {
+                        name: "total-cost".into(),
+                        parameters: vec![],
+                    }),
+                    Box::new(Expression::Number(5))
+                ),
+            ])
+        );
+        assert_eq!(domain.actions[0].constant_cost(), Some(5.0));
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_derived_typed_head() {
+        let domain_example = r"
+        (define (domain paths)
+            (:requirements :typing :derived-predicates)
+            (:types loc)
+            (:predicates (connected ?x - loc ?y - loc) (reachable ?x - loc))
+            (:derived (reachable ?x - loc) (exists (?y - loc) (connected ?y ?x)))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.derived.len(), 1);
+        assert_eq!(domain.derived[0].predicate.name, "reachable");
+        assert_eq!(
+            domain.derived[0].predicate.parameters,
+            vec![TypedParameter {
+                name: "?x".into(),
+                type_: "loc".into(),
+            }]
+        );
+        assert!(domain.infer_requirements().contains(&Requirement::DerivedPredicates));
+        let reparsed = Domain::parse(domain.to_pddl().as_str().into()).expect("Failed to reparse domain");
+        assert_eq!(reparsed, domain);
+    }
+
+    #[test]
+    fn test_allowed_requirements() {
+        let domain_example = r"
+        (define (domain ucpop)
+            (:requirements :ucpop :typing)
+            (:predicates (p) (q))
+            (:action act
+                :parameters ()
+                :precondition (p)
+                :effect (not (p))
+            )
+        )";
+        // Declarations are not checked, only the syntax of the features
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.requirements, vec![Requirement::Ucpop, Requirement::Typing]);
+
+        let strips_only = ParseOptions {
+            allowed_requirements: Some([Requirement::Strips].into_iter().collect()),
+            ..ParseOptions::default()
+        };
+        assert!(Domain::parse_with_options(domain_example.into(), &strips_only).is_ok());
+
+        let disjunctive = domain_example.replace(":precondition (p)", ":precondition (or (p) (q))");
+        assert_eq!(
+            Domain::parse_with_options(disjunctive.as_str().into(), &strips_only),
+            Err(ParserError::UnsupportedRequirement(Requirement::DisjunctivePreconditions))
+        );
+        let typed = domain_example.replace(":parameters ()", ":parameters (?x - block)");
+        assert_eq!(
+            Domain::parse_with_options(typed.as_str().into(), &strips_only),
+            Err(ParserError::UnsupportedRequirement(Requirement::Typing))
+        );
+
+        // Shorthands allow the features of the requirements they imply
+        let adl = ParseOptions {
+            allowed_requirements: Some([Requirement::Adl].into_iter().collect()),
+            ..ParseOptions::default()
+        };
+        assert!(Domain::parse_with_options(disjunctive.as_str().into(), &adl).is_ok());
+        assert!(Domain::parse_with_options(typed.as_str().into(), &adl).is_ok());
+
+        // Allowing every requirement does not make unsupported syntax parseable
+        let permissive = ParseOptions {
+            allowed_requirements: None,
+            ..ParseOptions::default()
+        };
+        let unsupported_syntax = domain_example.replace(":effect (not (p))", ":effect (p) :extra (p)");
+        assert!(Domain::parse_with_options(unsupported_syntax.as_str().into(), &permissive).is_err());
+    }
+
+    #[test]
+    fn test_supported_requirements_parse_by_default() {
+        let domain_example = r"
+        (define (domain adl)
+            (:requirements :strips :negative-preconditions :adl :preferences)
+            (:types block)
+            (:predicates (p) (q ?x - block))
+            (:action act
+                :parameters (?x - block)
+                :precondition (and
+                    (not (p))
+                    (or (q ?x) (exists (?y - block) (q ?y)))
+                    (forall (?y - block) (preference p0 (q ?y)))
+                )
+                :effect (when (q ?x) (p))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.requirements,
+            vec![
+                Requirement::Strips,
+                Requirement::NegativePreconditions,
+                Requirement::Adl,
+                Requirement::Preferences
+            ]
+        );
+        for requirement in Requirement::Adl.implied() {
+            assert!(Requirement::supported().contains(&requirement), "{requirement:?}");
+        }
+    }
+
+    #[test]
+    fn test_multiple_requirements_blocks() {
+        let domain_example = r"
+        (define (domain generated)
+            (:requirements :strips :typing)
+            (:requirements :typing :derived-predicates)
+            (:types block)
+            (:predicates (clear ?x - block))
+            (:action unstack
+                :parameters (?x - block)
+                :precondition (not (clear ?x))
+                :effect (clear ?x)
+            )
+        )";
+        let expected = vec![Requirement::Strips, Requirement::Typing, Requirement::DerivedPredicates];
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.requirements, expected);
+        assert_eq!(domain.types.len(), 1);
+
+        let (domain, errors) = Domain::parse_recoverable(domain_example.into());
+        assert!(errors.is_empty());
+        assert_eq!(domain.map(|domain| domain.requirements), Some(expected));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let domain_example = concat!(
+            "(define (domain broken)\n",
+            "  (:requirements :strips)\n",
+            "  ; predicates\n",
+            "  (:predicates (p) (q)\n",
+            "  (:action act :parameters () :effect (p))\n",
+            ")"
+        );
+        let error = Domain::parse(domain_example.into()).expect_err("Domain should not parse");
+        // The predicates section is not closed: the error is reported at the start of the section, i.e. the first token
+        // that could not be parsed.
+        assert_eq!(error.location(domain_example), Some((4, 3)));
+        assert!(matches!(error.inner(), ParserError::ExpectedToken(..)));
+        assert!(error.to_string().ends_with("(line 4, column 3)"));
+        assert_eq!(error.inner().location(domain_example), Some((4, 3)));
+
+        let crlf = domain_example.replace('\n', "\r\n");
+        let error = Domain::parse(crlf.as_str().into()).expect_err("Domain should not parse");
+        assert_eq!(error.location(&crlf), Some((4, 3)));
+
+        assert_eq!(ParserError::ExpectedFloat.location(domain_example), None);
+        assert_eq!(ParserError::ExpectedFloat.with_location(domain_example), ParserError::ExpectedFloat);
+    }
+
+    #[test]
+    fn test_either_type() {
+        let (_, type_) = Type::parse_type("(either a)".into()).expect("Type should parse");
+        assert_eq!(type_, Type::Simple("a".to_string()));
+        let (_, type_) = Type::parse_type("(either a b)".into()).expect("Type should parse");
+        assert_eq!(type_, Type::Either(vec!["a".to_string(), "b".to_string()]));
+
+        assert!(matches!(
+            Type::parse_type("(either)".into()),
+            Err(nom::Err::Failure(ParserError::EmptyEither(_)))
+        ));
+        let domain_example = concat!(
+            "(define (domain types)\n",
+            "  (:requirements :strips :typing)\n",
+            "  (:predicates (p ?x - (either)))\n",
+            ")"
+        );
+        let error = Domain::parse(domain_example.into()).expect_err("Domain should not parse");
+        assert!(matches!(error.inner(), ParserError::EmptyEither(_)));
+        assert_eq!(error.location(domain_example), Some((3, 31)));
+    }
+
+    #[test]
+    fn test_effect_summary() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        let pick_up = domain
+            .actions
+            .iter()
+            .find(|action| action.name() == "pick-up")
+            .expect("pick-up should exist");
+        assert_eq!(pick_up.effect_summary(), EffectSummary {
+            adds: 1,
+            deletes: 2,
+            numeric: 0,
+        });
+        assert_eq!(domain.action_effect_balance()["pick-up"], pick_up.effect_summary());
+    }
+
+    #[test]
+    fn test_max_arity() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.max_action_arity(), 3);
+        assert_eq!(domain.max_predicate_arity(), 2);
+        assert_eq!(domain.max_parameters_any(), 3);
+
+        let domain_example = r"
+        (define (domain wide)
+            (:requirements :strips :numeric-fluents)
+            (:predicates (p ?a))
+            (:functions (distance ?a ?b ?c ?d))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.max_action_arity(), 0);
+        assert_eq!(domain.max_predicate_arity(), 1);
+        assert_eq!(domain.max_parameters_any(), 4);
+    }
+
+    #[test]
+    fn test_parse_recoverable() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let (domain, errors) = Domain::parse_recoverable(domain_example.as_str().into());
+        assert!(errors.is_empty());
+        assert_eq!(domain, Domain::parse(domain_example.as_str().into()).ok());
+
+        let broken_example = concat!(
+            "(define (domain broken)\n",
+            "  (:requirements :strips)\n",
+            "  (:predicates (p) (q ?x))\n",
+            "  (:action first :parameters (?x) :precondition (p) :effect (q ?x))\n",
+            "  (:action broken :parameters (?x) :effect (increase))\n",
+            "  (:action second :parameters () :effect (p))\n",
+            "  (:action broken-too :parameters ?x :effect (p))\n",
+            "  stray\n",
+            ")"
+        );
+        let (domain, errors) = Domain::parse_recoverable(broken_example.into());
+        let domain = domain.expect("The header should parse");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(domain.requirements, vec![Requirement::Strips]);
+        assert_eq!(domain.predicates.len(), 2);
+        assert_eq!(
+            domain.actions.iter().map(domain::action::Action::name).collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+
+        let (domain, errors) = Domain::parse_recoverable("(define (problem broken))".into());
+        assert!(domain.is_none());
+        assert_eq!(errors.len(), 1);
+
+        let stream = TokenStream::new("(a (b)) c (d) e)").resync();
+        assert_eq!(stream.peek().map(|(_, s)| s), Some("("));
+        let stream = stream.advance().advance().advance().resync();
+        assert_eq!(stream.peek().map(|(_, s)| s), Some(")"));
+        assert!(TokenStream::new("(a (b)").resync().is_empty());
+    }
+
+    #[test]
+    fn test_domain_name_with_digits() {
+        let domain_example = concat!(
+            "(define (domain blocks-world-4ops)\n",
+            "  (:requirements :strips :typing)\n",
+            "  (:types block)\n",
+            "  (:predicates (clear ?x - block))\n",
+            ")"
+        );
+        let problem_example = concat!(
+            "(define (problem blocks_4-1) (:domain blocks-world-4ops)\n",
+            "  (:objects a b - block)\n",
+            "  (:init (clear a) (clear b))\n",
+            "  (:goal (clear a))\n",
+            ")"
+        );
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(domain.name, "blocks-world-4ops");
+        assert_eq!(problem.domain, "blocks-world-4ops");
+        assert_eq!(problem.name, "blocks_4-1");
+        assert_eq!(problem.validate_against(&domain), Ok(()));
+
+        let other = Problem {
+            domain: "blocks-world-3ops".to_string(),
+            objects: vec![Object::new("c", "cube")],
+            ..problem
+        };
+        assert_eq!(other.validate_against(&domain).map_err(|errors| errors.len()), Err(2));
+    }
+
+    #[test]
+    fn test_problem_validate_against_predicates() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let problem_example = include_str!("../tests/problem.pddl");
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(problem.validate_against(&domain), Ok(()));
+
+        let problem_example = problem_example
+            .replace("(:domain letseat)", "(:domain lets-eat)")
+            .replace("cupcake - cupcake", "cupcake - cake")
+            .replace("(arm-empty)", "(arm-free arm)")
+            .replace("(path table plate)", "(path table)")
+            .replace("(on cupcake plate)", "(exists (?x ?y) (and (on ?x plate) (not (= ?x ?y))))");
+        let problem = Problem::parse(problem_example.as_str().into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.validate_against(&domain),
+            Err(vec![
+                ValidationError::DomainMismatch {
+                    problem: "letseat-simple".into(),
+                    expected: "letseat".into(),
+                    found: "lets-eat".into(),
+                },
+                ValidationError::UndeclaredType {
+                    name: "cake".into(),
+                    context: "object `cupcake`".into(),
+                },
+                ValidationError::ProblemUndeclaredPredicate {
+                    problem: "letseat-simple".into(),
+                    predicate: "arm-free".into(),
+                },
+                ValidationError::ProblemArityMismatch {
+                    problem: "letseat-simple".into(),
+                    predicate: "path".into(),
+                    expected: 2,
+                    found: 1,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.to_string(), domain.to_pddl());
+        assert_eq!(format!("{}", domain.actions[0].effect()), domain.actions[0].effect().to_pddl());
+        let domain::action::Action::Simple(action) = &domain.actions[0] else {
+            unreachable!("The first action of the domain is a simple action")
+        };
+        assert_eq!(action.to_string(), action.to_pddl());
+
+        let durative_example =
+            std::fs::read_to_string("tests/durative-actions-domain.pddl").expect("Failed to read domain file");
+        let durative = Domain::parse(durative_example.as_str().into()).expect("Failed to parse domain");
+        let domain::action::Action::Durative(action) = &durative.actions[0] else {
+            unreachable!("The first action of the domain is a durative action")
+        };
+        assert_eq!(action.to_string(), action.to_pddl());
+
+        let problem_example = std::fs::read_to_string("tests/problem.pddl").expect("Failed to read problem file");
+        let problem = Problem::parse(problem_example.as_str().into()).expect("Failed to parse problem");
+        assert_eq!(format!("{problem}"), problem.to_pddl());
+    }
+
+    #[test]
+    fn test_function_comparison() {
+        let domain_example = concat!(
+            "(define (domain travel)\n",
+            "  (:requirements :strips :typing :numeric-fluents)\n",
+            "  (:types vehicle place)\n",
+            "  (:predicates (located ?v - vehicle ?p - place))\n",
+            "  (:functions (distance ?a ?b - place) (fuel ?v - vehicle))\n",
+            "  (:action drive\n",
+            "    :parameters (?v - vehicle ?a ?b - place)\n",
+            "    :precondition (and (located ?v ?a) (>= (fuel ?v) (distance ?a ?b)))\n",
+            "    :effect (and (not (located ?v ?a)) (located ?v ?b) (decrease (fuel ?v) (distance ?a ?b))))\n",
+            ")"
+        );
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let atom = |name: &str, parameters: &[&str]| Expression::Atom {
+            name: name.to_string(),
+            parameters: parameters.iter().map(|&p| p.into()).collect(),
+        };
+        let Some(Expression::And(conditions)) = domain.actions[0].precondition() else {
+            unreachable!("The precondition is a conjunction")
+        };
+        assert_eq!(
+            conditions[1],
+            Expression::BinaryOp(
+                BinaryOp::GreaterThanOrEqual,
+                Box::new(atom("fuel", &["?v"])),
+                Box::new(atom("distance", &["?a", "?b"])),
+            )
+        );
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+    }
+
+    #[test]
+    fn test_sort_domain() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let mut domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        domain.sort_actions_by_name();
+        domain.sort_predicates_by_name();
+        let names = domain.actions.iter().map(domain::action::Action::name).collect::<Vec<_>>();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        assert_eq!(names, sorted);
+        assert!(domain.predicates.windows(2).all(|w| w[0].name <= w[1].name));
+
+        let output = domain.to_pddl();
+        let positions = names
+            .iter()
+            .map(|name| output.find(&format!("(:action {name}")))
+            .collect::<Option<Vec<_>>>()
+            .expect("Every action should be emitted");
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(Domain::parse(output.as_str().into()), Ok(domain));
+
+        let types_example =
+            "(define (domain types) (:requirements :typing) (:types truck - vehicle vehicle - locatable locatable))";
+        let mut types = Domain::parse(types_example.into()).expect("Failed to parse domain");
+        types.sort_types_topologically();
+        assert_eq!(
+            types.types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["locatable", "vehicle", "truck"]
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.ancestors("robot"), vec!["bot", "locatable", "object"]);
+        assert_eq!(domain.ancestors("location"), vec!["object"]);
+        assert_eq!(domain.ancestors("undeclared"), vec!["object"]);
+        assert!(domain.ancestors("object").is_empty());
+        assert!(domain.is_subtype("robot", "bot"));
+        assert!(domain.is_subtype("robot", "locatable"));
+        assert!(domain.is_subtype("robot", "robot"));
+        assert!(domain.is_subtype("location", "object"));
+        assert!(!domain.is_subtype("bot", "robot"));
+        assert!(!domain.is_subtype("cupcake", "bot"));
+
+        let cyclic = Domain {
+            types: vec![
+                TypeDef {
+                    name: "a".to_string(),
+                    parent: Some("b".to_string()),
+                },
+                TypeDef {
+                    name: "b".to_string(),
+                    parent: Some("a".to_string()),
+                },
+            ],
+            ..domain
+        };
+        assert_eq!(cyclic.ancestors("a"), vec!["b"]);
+        assert!(cyclic.is_subtype("a", "b"));
+        assert!(!cyclic.is_subtype("a", "c"));
+    }
+
+    #[test]
+    fn test_lookup_by_name() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.get_action("PICK-UP").map(domain::action::Action::name), Some("pick-up"));
+        assert_eq!(domain.get_action("pick-up"), domain.get_action("Pick-Up"));
+        assert!(domain.get_action("teleport").is_none());
+        assert_eq!(domain.get_predicate("ON").map(|p| p.name.as_str()), Some("on"));
+        assert!(domain.get_predicate("pick-up").is_none());
+        assert!(domain.get_function("on").is_none());
+
+        let numeric_example = "(define (domain numeric) (:requirements :numeric-fluents) (:functions (fuel ?v)))";
+        let numeric = Domain::parse(numeric_example.into()).expect("Failed to parse domain");
+        assert_eq!(numeric.get_function("Fuel").map(|f| f.parameters.len()), Some(1));
+    }
+
+    #[test]
+    fn test_plan_parameter_names() {
+        let plan_example = "(drive truck-1 city_2 ?x)\n(unload Truck_10 pkg-3-b city-2_north)\n";
+        let plan = Plan::parse(plan_example.into()).expect("Failed to parse plan");
+        assert_eq!(
+            plan.0[0],
+            Action::Simple(SimpleAction {
+                name: "drive".to_string(),
+                parameters: vec!["truck-1".into(), "city_2".into(), "?x".into()],
+            })
+        );
+        assert!(plan.0[0].parameters()[2].is_variable());
+        assert_eq!(plan.0[1].parameters().len(), 3);
+        let output = plan.0.iter().map(|action| format!("{action}\n")).collect::<String>();
+        assert_eq!(output, plan_example);
+        assert_eq!(Plan::parse(output.as_str().into()), Ok(plan));
+    }
+
+    #[test]
+    fn test_occurrences_of() {
+        let (_, effect) = Expression::parse_expression(
+            "(and (on ?x ?z) (not (on ?x ?y)) (clear ?y) (not (clear ?z)) (forall (?b) (when (on ?b ?x) (moved ?b))))"
+                .into(),
+        )
+        .expect("Failed to parse expression");
+        assert_eq!(effect.occurrences_of("on"), 3);
+        assert_eq!(effect.occurrences_of("clear"), 2);
+        assert_eq!(effect.occurrences_of("moved"), 1);
+        assert_eq!(effect.occurrences_of("holding"), 0);
+    }
+
+    #[test]
+    fn test_expression_traversal() {
+        let (_, effect) = Expression::parse_expression(
+            concat!(
+                "(and (at end (not (on ?x ?y))) (when (or (clear ?y) (not (free))) (holding ?x)) ",
+                "(forall (?b) (increase (load ?b) (* 2 (weight ?x)))) (assign (total) ?d))"
+            )
+            .into(),
+        )
+        .expect("Failed to parse expression");
+        let atoms = effect
+            .atoms()
+            .map(|(name, parameters)| (name, parameters.iter().map(Parameter::as_str).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(atoms, vec![
+            ("on", vec!["?x", "?y"]),
+            ("clear", vec!["?y"]),
+            ("free", vec![]),
+            ("holding", vec!["?x"]),
+            ("load", vec!["?b"]),
+            ("weight", vec!["?x"]),
+            ("total", vec![]),
+        ]);
+
+        let mut nodes = 0;
+        let mut numbers = Vec::new();
+        effect.visit(&mut |expression| {
+            nodes += 1;
+            if let Expression::Number(n) = expression {
+                numbers.push(*n);
+            }
+        });
+        assert_eq!(numbers, vec![2]);
+        assert_eq!(nodes, 19);
+    }
+
+    #[test]
+    fn test_expression_pretty_tree() {
+        let (_, expression) = Expression::parse_expression(
+            "(and (on ?arm ?loc) (not (holding ?arm)) (forall (?b - box) (> (weight ?b) 2.5)))".into(),
+        )
+        .expect("Failed to parse expression");
+        assert_eq!(
+            expression.pretty_tree(),
+            "And\n  Atom on [?arm ?loc]\n  Not\n    Atom holding [?arm]\n  Forall [?b - box]\n    BinaryOp >\n      \
+             Atom weight [?b]\n      Float 2.5"
+        );
+    }
+
+    #[test]
+    fn test_free_variables() {
+        let parse = |input: &str| Expression::parse_expression(input.into()).expect("Failed to parse expression").1;
+        let expression = parse("(and (on ?x ?y) (forall (?x) (when (on ?x ?z) (clear ?x))) (clear ?y) (>= ?d 0))");
+        assert_eq!(expression.free_variables(), vec!["?x", "?y", "?z", "?d"]);
+
+        let shadowed = parse("(and (forall (?x) (on ?x ?y)) (exists (?y) (on ?y ?x)))");
+        assert_eq!(shadowed.free_variables(), vec!["?y", "?x"]);
+
+        let closed = parse("(forall (?x ?y) (exists (?z) (and (on ?x ?z) (on ?z ?y))))");
+        assert!(closed.free_variables().is_empty());
+    }
+
+    #[test]
+    fn test_timed_constraints() {
+        let (_, expression) = Expression::parse_expression("(hold-after 10 (safe))".into()).expect("Failed to parse");
+        assert_eq!(
+            expression,
+            Expression::HoldAfter(
+                OrderedFloat(10.0),
+                Box::new(Expression::Atom {
+                    name: "safe".into(),
+                    parameters: vec![],
+                })
+            )
+        );
+        assert_eq!(expression.to_pddl(), "(hold-after 10 (safe ))");
+        assert_eq!(Expression::parse_expression(expression.to_pddl().as_str().into()).map(|(_, e)| e), Ok(expression));
+
+        let problem_example = r"
+        (define (problem deliver)
+            (:domain logistics)
+            (:objects pkg - package)
+            (:init (at-depot pkg))
+            (:goal (delivered pkg))
+            (:constraints (and (hold-during 2.5 8 (at-depot pkg)) (hold-after 10 (delivered pkg))))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        let Some(Expression::And(constraints)) = &problem.constraints else {
+            unreachable!("The constraints are a conjunction")
+        };
+        assert!(matches!(constraints[0], Expression::HoldDuring(start, end, _) if start.0 == 2.5 && end.0 == 8.0));
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_parse_owned() {
+        let predicates = (0..3).map(|i| format!("(p{i})")).collect::<String>();
+        let domain = Domain::parse_owned(format!("(define (domain owned) (:predicates {predicates}))"))
+            .expect("Failed to parse domain");
+        assert_eq!(domain.predicates.len(), 3);
+
+        let problem = Problem::parse_owned(format!(
+            "(define (problem owned-1) (:domain {}) (:objects) (:init (p0)) (:goal (p2)))",
+            domain.name
+        ))
+        .expect("Failed to parse problem");
+        assert_eq!(problem.domain, "owned");
+
+        let plan = Plan::parse_owned((0..2).map(|i| format!("(step s{i})\n")).collect()).expect("Failed to parse plan");
+        assert_eq!(plan.0.len(), 2);
+        assert!(Plan::parse_owned(String::from("(step")).is_err());
+    }
+
+    #[test]
+    fn test_domain_constraints() {
+        let domain_example = concat!(
+            "(define (domain cleaning)\n",
+            "  (:requirements :strips :typing :constraints)\n",
+            "  (:types room)\n",
+            "  (:predicates (clean ?x - room))\n",
+            "  (:constraints (forall (?x - room) (always (clean ?x))))\n",
+            "  (:action mop :parameters (?x - room) :effect (clean ?x))\n",
+            ")"
+        );
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let Some(Expression::Forall(_, constraint)) = &domain.constraints else {
+            unreachable!("The constraints are quantified")
+        };
+        assert_eq!(
+            **constraint,
+            Expression::Always(Box::new(Expression::Atom {
+                name: "clean".into(),
+                parameters: vec!["?x".into()],
+            }))
+        );
+        assert!(domain.infer_requirements().contains(&Requirement::Constraints));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()).as_ref(), Ok(&domain));
+
+        let (recovered, errors) = Domain::parse_recoverable(domain_example.into());
+        assert!(errors.is_empty());
+        assert_eq!(recovered, Some(domain));
+    }
+
+    #[test]
+    fn test_modal_operators() {
+        let parse = |input: &str| Expression::parse_expression(input.into()).map(|(_, expression)| expression);
+        let delivered = Box::new(Expression::Atom {
+            name: "delivered".into(),
+            parameters: vec!["?p".into()],
+        });
+        let loaded = Box::new(Expression::Atom {
+            name: "loaded".into(),
+            parameters: vec!["?p".into()],
+        });
+        let cases = [
+            ("(within 10 (delivered ?p))", Expression::Within(OrderedFloat(10.0), delivered.clone())),
+            ("(at-most-once (delivered ?p))", Expression::AtMostOnce(delivered.clone())),
+            (
+                "(sometime-after (loaded ?p) (delivered ?p))",
+                Expression::SometimeAfter(loaded.clone(), delivered.clone()),
+            ),
+            (
+                "(sometime-before (delivered ?p) (loaded ?p))",
+                Expression::SometimeBefore(delivered.clone(), loaded.clone()),
+            ),
+            (
+                "(always-within 2.5 (loaded ?p) (delivered ?p))",
+                Expression::AlwaysWithin(OrderedFloat(2.5), loaded, delivered.clone()),
+            ),
+            ("(always (delivered ?p))", Expression::Always(delivered)),
+        ];
+        for (input, expected) in cases {
+            let expression = parse(input).expect("Failed to parse expression");
+            assert_eq!(expression, expected);
+            assert_eq!(parse(&expression.to_pddl()), Ok(expression));
+        }
+        assert!(parse("(sometime-after (loaded ?p))").is_err());
+        assert!(parse("(within (delivered ?p))").is_err());
+
+        let problem_example = r"
+        (define (problem deliver)
+            (:domain logistics)
+            (:objects pkg - package)
+            (:init (at-depot pkg))
+            (:goal (delivered pkg))
+            (:constraints (and (within 10 (delivered pkg)) (at-most-once (at-depot pkg))))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_negated_conjunction() {
+        let atom = |name: &str| Expression::Atom {
+            name: name.into(),
+            parameters: vec![],
+        };
+        let not = |expression: Expression| Expression::Not(Box::new(expression));
+        let (_, expression) = Expression::parse_expression("(not (and (p) (q)))".into()).expect("Failed to parse");
+        assert_eq!(expression, not(Expression::And(vec![atom("p"), atom("q")])));
+        assert_eq!(expression.to_nnf(), Expression::Or(vec![not(atom("p")), not(atom("q"))]));
+        assert_eq!(expression.to_nnf().to_pddl(), "(or (not (p )) (not (q )))");
+
+        let (_, nested) = Expression::parse_expression("(and (r) (not (or (p) (not (and (q) (not (r)))))))".into())
+            .expect("Failed to parse");
+        assert_eq!(
+            nested.to_nnf(),
+            Expression::And(vec![
+                atom("r"),
+                Expression::And(vec![not(atom("p")), Expression::And(vec![atom("q"), not(atom("r"))])]),
+            ])
+        );
+
+        let (_, quantified) = Expression::parse_expression("(not (forall (?x) (exists (?y) (on ?x ?y))))".into())
+            .expect("Failed to parse");
+        assert_eq!(
+            quantified.to_nnf().to_pddl(),
+            "(exists (?x - object) (forall (?y - object) (not (on ?x ?y))))"
+        );
+        assert_eq!(nested.to_nnf().to_nnf(), nested.to_nnf());
+    }
+
+    #[test]
+    fn test_objects_by_type() {
+        let problem_example = std::fs::read_to_string("tests/problem.pddl").expect("Failed to read problem file");
+        let problem = Problem::parse(problem_example.as_str().into()).expect("Failed to parse problem");
+        let objects = problem.objects_by_type();
+        let names = |type_: &str| objects[type_].iter().map(|o| o.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names("location"), vec!["table", "plate"]);
+        assert_eq!(names("robot"), vec!["arm"]);
+        assert_eq!(names("cupcake"), vec!["cupcake"]);
+        assert!(!objects.contains_key("locatable"));
+        assert_eq!(objects.len(), 3);
+    }
+
+    #[test]
+    fn test_multiple_derived() {
+        let domain_example = r"
+        (define (domain blocks-derived)
+            (:requirements :strips :derived-predicates :disjunctive-preconditions :existential-preconditions)
+            (:predicates (on ?x ?y) (above ?x ?y) (clear ?x) (free ?x))
+            (:derived (above ?x ?y) (or (on ?x ?y) (exists (?z) (and (on ?x ?z) (above ?z ?y)))))
+            (:action unstack
+                :parameters (?x ?y)
+                :precondition (and (on ?x ?y) (free ?x))
+                :effect (and (not (on ?x ?y)) (clear ?y))
+            )
+            (:derived (free ?x) (not (exists (?z) (on ?z ?x))))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.derived.iter().map(|d| d.predicate.name.as_str()).collect::<Vec<_>>(),
+            vec!["above", "free"]
+        );
+        assert_eq!(domain.actions.len(), 1);
+        assert!(matches!(domain.derived[0].body, Expression::Or(_)));
+        let output = domain.to_pddl();
+        assert_eq!(output.matches("(:derived").count(), 2);
+        assert_eq!(Domain::parse(output.as_str().into()), Ok(domain));
+    }
+
+    #[test]
+    fn test_duration_inequalities() {
+        let domain_example = r"
+        (define (domain charging)
+            (:requirements :durative-actions :durative-inequalities)
+            (:predicates (charged ?r))
+            (:durative-action charge
+                :parameters (?r)
+                :duration (and (>= ?duration 5) (<= ?duration 10))
+                :condition (at start (not (charged ?r)))
+                :effect (at end (charged ?r))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let domain::action::Action::Durative(action) = &domain.actions[0] else {
+            unreachable!("The action is a durative action")
+        };
+        let duration = Box::new(Expression::Term("?duration".into()));
+        assert_eq!(
+            action.duration,
+            Expression::And(vec![
+                Expression::BinaryOp(BinaryOp::GreaterThanOrEqual, duration.clone(), Box::new(Expression::Number(5))),
+                Expression::BinaryOp(BinaryOp::LessThanOrEqual, duration, Box::new(Expression::Number(10))),
+            ])
+        );
+        assert!(domain.infer_requirements().contains(&Requirement::DurativeInequalities));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+    }
+
+    #[test]
+    fn test_equality_without_requirement() {
+        let domain_example = r"
+        (define (domain blocks)
+            (:requirements :strips :numeric-fluents)
+            (:predicates (on ?x ?y) (clear ?x))
+            (:functions (moves))
+            (:action move
+                :parameters (?x ?y)
+                :precondition (and (clear ?x) (clear ?y) (not (= ?x ?y)) (= (moves) 0))
+                :effect (on ?x ?y)
+            )
+        )";
+        let problem_example = r"
+        (define (problem stack)
+            (:domain blocks)
+            (:objects a b)
+            (:init (clear a) (clear b))
+            (:goal (exists (?x ?y) (and (on ?x ?y) (not (= ?x ?y)))))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(domain.uses_equality_without_requirement(), vec![
+            "Action `move` uses `(= ?x ?y)` without the `:equality` requirement"
+        ]);
+        assert_eq!(problem.uses_equality_without_requirement(&domain), vec![
+            "Problem `stack` uses `(= ?x ?y)` without the `:equality` requirement"
+        ]);
+
+        let domain_example = domain_example.replace(":numeric-fluents", ":numeric-fluents :equality");
+        let domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.requirements, vec![
+            Requirement::Strips,
+            Requirement::NumericFluents,
+            Requirement::Equality
+        ]);
+        assert!(domain.uses_equality_without_requirement().is_empty());
+        assert!(problem.uses_equality_without_requirement(&domain).is_empty());
+    }
+
+    #[test]
+    fn test_requirement_consistency() {
+        let domain_example = r"
+        (define (domain trucks)
+            (:requirements :strips)
+            (:predicates (moved ?t) (loaded ?t))
+            (:functions (fuel ?t))
+            (:action drive
+                :parameters (?t ?u)
+                :precondition (and (> (fuel ?t) 0) (not (= ?t ?u)))
+                :effect (moved ?t)
+            )
+            (:durative-action load
+                :parameters (?t)
+                :duration (= ?duration 2)
+                :condition (at start (moved ?t))
+                :effect (at end (loaded ?t))
+            )
+        )";
+        let mut domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.validate_requirement_consistency(), vec![
+            "Function `fuel` is declared without the `:numeric-fluents` requirement",
+            "Action `drive` uses `(> (fuel ?t) 0)` without the `:numeric-fluents` requirement",
+            "Durative action `load` is defined without the `:durative-actions` requirement",
+        ]);
+
+        domain.with_requirement(Requirement::Fluents);
+        domain.with_requirement(Requirement::DurativeActions);
+        assert!(domain.validate_requirement_consistency().is_empty());
+    }
+
+    #[test]
+    fn test_action_accessors() {
+        let domain_example = r"
+        (define (domain mixed)
+            (:requirements :strips :durative-actions)
+            (:predicates (ready) (done))
+            (:action prepare :parameters () :effect (ready))
+            (:durative-action work
+                :parameters ()
+                :duration (= ?duration 3)
+                :condition (at start (ready))
+                :effect (at end (done))
+            )
+        )";
+        let mut domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(domain.actions[0].as_simple().map(|a| a.name.as_str()), Some("prepare"));
+        assert!(domain.actions[0].as_durative().is_none());
+        assert_eq!(domain.actions[1].as_durative().map(|a| a.name.as_str()), Some("work"));
+        assert!(domain.actions[1].as_simple().is_none());
+
+        if let Some(action) = domain.actions[0].as_simple_mut() {
+            action.name = "setup".to_string();
+        }
+        if let Some(action) = domain.actions[1].as_durative_mut() {
+            action.duration = Expression::BinaryOp(
+                BinaryOp::Equal,
+                Box::new(Expression::Term("?duration".into())),
+                Box::new(Expression::Number(5)),
+            );
+        }
+        assert!(domain.actions[0].as_durative_mut().is_none());
+        assert!(domain.actions[1].as_simple_mut().is_none());
+        assert_eq!(domain.actions[0].name(), "setup");
+        assert!(domain.to_pddl().contains(":duration (= ?duration 5)"));
+    }
+
+    #[test]
+    fn test_timed_initial_literals() {
+        let problem_example = r"
+        (define (problem night)
+            (:domain lights)
+            (:objects hall - room)
+            (:init (dark hall) (at 5 (light-on hall)) (at 10.5 (not (light-on hall))))
+            (:goal (visited hall))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        let light_on = Expression::Atom {
+            name: "light-on".into(),
+            parameters: vec!["hall".into()],
+        };
+        assert_eq!(problem.init, vec![
+            Expression::Atom {
+                name: "dark".into(),
+                parameters: vec!["hall".into()],
+            },
+            Expression::TimedLiteral(OrderedFloat(5.0), Box::new(light_on.clone())),
+            Expression::TimedLiteral(OrderedFloat(10.5), Box::new(Expression::Not(Box::new(light_on)))),
+        ]);
+        assert_eq!(problem.init[1].to_pddl(), "(at 5 (light-on hall))");
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+
+        let (_, duration) = Expression::parse_expression("(at end (light-on hall))".into()).expect("Failed to parse");
+        assert!(matches!(duration, Expression::Duration(DurationInstant::End, _)));
+    }
+
+    #[test]
+    fn test_numeric_init() {
+        let problem_example = r"
+        (define (problem refuel)
+            (:domain trucks)
+            (:objects truck1 truck2 - truck depot city - location)
+            (:init
+                (located truck1 depot)
+                (= (fuel truck1) 100)
+                (= (fuel truck2) 42.5)
+                (= (distance depot city) 12)
+                (= (total-cost) 0)
+            )
+            (:goal (located truck1 city))
+            (:metric minimize (total-cost))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        let fluent = |name: &str, parameters: &[&str]| {
+            Box::new(Expression::Atom {
+                name: name.into(),
+                parameters: parameters.iter().map(|&p| p.into()).collect(),
+            })
+        };
+        assert_eq!(problem.init[1..], [
+            Expression::BinaryOp(BinaryOp::Equal, fluent("fuel", &["truck1"]), Box::new(Expression::Number(100))),
+            Expression::BinaryOp(
+                BinaryOp::Equal,
+                fluent("fuel", &["truck2"]),
+                Box::new(Expression::Float(OrderedFloat(42.5)))
+            ),
+            Expression::BinaryOp(
+                BinaryOp::Equal,
+                fluent("distance", &["depot", "city"]),
+                Box::new(Expression::Number(12))
+            ),
+            Expression::BinaryOp(BinaryOp::Equal, fluent("total-cost", &[]), Box::new(Expression::Number(0))),
+        ]);
+        assert!(problem.to_pddl().contains("(= (fuel truck2) 42.5)"));
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_fluent_to_fluent_init() {
+        let problem_example = r"
+        (define (problem company)
+            (:domain staff)
+            (:objects emp1 emp2 - employee)
+            (:init
+                (= (boss-of emp1) (ceo))
+                (= (salary emp2) (salary emp1))
+            )
+            (:goal (hired emp2))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        let fluent = |name: &str, parameters: &[&str]| {
+            Box::new(Expression::Atom {
+                name: name.into(),
+                parameters: parameters.iter().map(|&p| p.into()).collect(),
+            })
+        };
+        assert_eq!(problem.init, vec![
+            Expression::BinaryOp(BinaryOp::Equal, fluent("boss-of", &["emp1"]), fluent("ceo", &[])),
+            Expression::BinaryOp(BinaryOp::Equal, fluent("salary", &["emp2"]), fluent("salary", &["emp1"])),
+        ]);
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_skip_unknown_init_facts() {
+        let problem_example = r"
+        (define (problem night)
+            (:domain lights)
+            (:objects hall - room)
+            (:init (dark hall) (at-end (light-on hall)) (at 5 (light-on hall)) (= (power) 3))
+            (:goal (visited hall))
+        )";
+        assert!(Problem::parse(problem_example.into()).is_err());
+
+        let lenient = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let problem = Problem::parse_with_options(problem_example.into(), &lenient).expect("Failed to parse problem");
+        assert_eq!(problem.init.len(), 3);
+        assert!(matches!(problem.init[1], Expression::TimedLiteral(..)));
+        assert_eq!(problem.skipped_init, vec!["at-end"]);
+        assert!(!problem.to_pddl().contains("at-end"));
+    }
+
+    #[test]
+    fn test_redundant_goal_parentheses() {
+        let problem_example = r"
+        (define (problem dinner)
+            (:domain letseat)
+            (:objects cupcake plate)
+            (:init (on cupcake plate))
+            (:goal ((and (on cupcake plate) (arm-empty))))
+        )";
+        let error = Problem::parse(problem_example.into()).err();
+        assert!(matches!(error.as_ref().map(ParserError::inner), Some(ParserError::RedundantParentheses(_))));
+        assert_eq!(error.and_then(|error| error.location(problem_example)), Some((6, 20)));
+
+        let lenient = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let problem = Problem::parse_with_options(problem_example.into(), &lenient).expect("Failed to parse problem");
+        let single = Problem::parse(problem_example.replace("((and", "(and").replace(")))\n", "))\n").as_str().into())
+            .expect("Failed to parse problem");
+        assert_eq!(problem, single);
+    }
+
+    #[test]
+    fn test_missing_requirements() {
+        let domain_example = std::fs::read_to_string("tests/domain.pddl").expect("Failed to read domain file");
+        let mut domain = Domain::parse(domain_example.as_str().into()).expect("Failed to parse domain");
+        assert_eq!(domain.requirements, vec![Requirement::Typing]);
+        let planner = [Requirement::Typing, Requirement::DurativeActions, Requirement::Typing];
+        assert_eq!(domain.missing_requirements(&planner), vec![Requirement::DurativeActions]);
+        assert!(domain.missing_requirements(&[]).is_empty());
+
+        domain.requirements = vec![Requirement::Adl];
+        assert!(domain
+            .missing_requirements(&[
+                Requirement::Strips,
+                Requirement::Equality,
+                Requirement::UniversalPreconditions,
+                Requirement::ConditionalEffects,
+            ])
+            .is_empty());
+        assert_eq!(domain.missing_requirements(&[Requirement::NumericFluents]), vec![Requirement::NumericFluents]);
+        assert!(Requirement::Ucpop.implied().contains(&Requirement::ExistentialPreconditions));
+        assert_eq!(Requirement::Strips.implied(), vec![Requirement::Strips]);
+    }
+
+    #[test]
+    fn test_either_everywhere() {
+        let domain_example = r"
+        (define (domain mixed-crew)
+            (:requirements :strips :typing)
+            (:types robot human room)
+            (:constants c1 c2 - (either robot human) lobby - room)
+            (:predicates (in ?agent - (either robot human) ?r - room))
+            (:action enter
+                :parameters (?agent - (either robot human) ?r - room)
+                :precondition (in ?agent lobby)
+                :effect (in ?agent ?r)
+            )
+        )";
+        let problem_example = r"
+        (define (problem shift)
+            (:domain mixed-crew)
+            (:objects o1 - (either robot human) kitchen - room)
+            (:init (in o1 lobby))
+            (:goal (in o1 kitchen))
+        )";
+        let crew = Type::Either(vec!["robot".into(), "human".into()]);
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.constants.iter().map(|c| (c.name.as_str(), &c.type_)).collect::<Vec<_>>(),
+            vec![("c1", &crew), ("c2", &crew), ("lobby", &Type::Simple("room".into()))]
+        );
+        assert_eq!(domain.predicates[0].parameters[0].type_, crew);
+        assert_eq!(domain.actions[0].parameters()[0].type_, crew);
+        assert!(domain.to_pddl().contains("c1 - (either robot human)"));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()).as_ref(), Ok(&domain));
+
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(problem.objects[0], Object::new("o1", crew));
+        assert_eq!(problem.validate_against(&domain), Ok(()));
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_empty_effects() {
+        let domain_example = "(define (domain noop)
+            (:requirements :strips)
+            (:predicates (ready))
+            (:action wait
+                :parameters ()
+                :precondition (ready)
+                :effect (and))
+            (:action check
+                :parameters ()
+                :precondition (ready))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let effects = domain
+            .actions
+            .iter()
+            .map(|action| match action {
+                domain::action::Action::Simple(action) => &action.effect,
+                domain::action::Action::Durative(action) => &action.effect,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(effects, vec![&Expression::And(vec![]), &Expression::And(vec![])]);
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+    }
+
+    #[test]
+    fn test_negated_comparisons_and_quantifiers() {
+        let parse = |input: &str| {
+            let (output, expression) = Expression::parse_expression(input.into()).expect("Failed to parse expression");
+            assert!(output.is_empty());
+            expression
+        };
+        let atom = |name: &str, parameter: &str| Expression::Atom {
+            name: name.into(),
+            parameters: vec![parameter.into()],
+        };
+        let var = |name: &str| Box::new(Expression::Term(name.into()));
+        let not = |expression: Expression| Expression::Not(Box::new(expression));
+        assert_eq!(
+            parse("(not (= ?x ?y))"),
+            not(Expression::BinaryOp(BinaryOp::Equal, var("?x"), var("?y")))
+        );
+        assert_eq!(
+            parse("(not (>= (fuel ?v) 10))"),
+            not(Expression::BinaryOp(
+                BinaryOp::GreaterThanOrEqual,
+                Box::new(atom("fuel", "?v")),
+                Box::new(Expression::Number(10))
+            ))
+        );
+        let Expression::Not(forall) = parse("(not (forall (?x) (p ?x)))") else {
+            unreachable!("Expected a negation");
+        };
+        assert!(matches!(*forall, Expression::Forall(..)));
+        assert_eq!(parse("(not (or (p ?x) (q ?x)))"), not(Expression::Or(vec![atom("p", "?x"), atom("q", "?x")])));
+        let Expression::Not(always) = parse("(not (always (p ?x)))") else {
+            unreachable!("Expected a negation");
+        };
+        assert!(matches!(*always, Expression::Always(..)));
+    }
+
+    #[test]
+    fn test_keywords_as_predicate_names() {
+        let domain_example = "(define (domain keywords)
+            (:requirements :strips)
+            (:predicates (not ?r) (not-something ?r) (ready))
+            (:action flip
+                :parameters (?r)
+                :precondition (and (not-something ?r) (not (ready)))
+                :effect (and (not ?r) (not (not ?r))))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.predicates.iter().map(|predicate| predicate.name.as_str()).collect::<Vec<_>>(),
+            vec!["not", "not-something", "ready"]
+        );
+        let atom = |name: &str, parameters: &[&str]| Expression::Atom {
+            name: name.into(),
+            parameters: parameters.iter().map(|&parameter| parameter.into()).collect(),
+        };
+        let domain::action::Action::Simple(action) = &domain.actions[0] else {
+            unreachable!("Expected a simple action");
+        };
+        assert_eq!(
+            action.precondition,
+            Some(Expression::And(vec![
+                atom("not-something", &["?r"]),
+                Expression::Not(Box::new(atom("ready", &[]))),
+            ]))
+        );
+        assert_eq!(
+            action.effect,
+            Expression::And(vec![atom("not", &["?r"]), Expression::Not(Box::new(atom("not", &["?r"])))])
+        );
+        assert_eq!(domain.validate(), Ok(()));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+
+        let domain_example = "(define (domain reserved)
+            (:requirements :strips)
+            (:predicates (imply ?x ?y) (when ?x) (preference ?x) (or))
+            (:action check
+                :parameters (?x ?y)
+                :precondition (and (imply ?x ?y) (or) (or (when ?x) (preference ?y)))
+                :effect (not (imply ?x ?y)))
+        )";
+        let problem_example = "(define (problem reserved)
+            (:domain reserved)
+            (:objects a b c)
+            (:init (imply a b) (when c) (preference a))
+            (:goal (and (imply c b) (not (when a))))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.predicates.iter().map(|predicate| predicate.name.as_str()).collect::<Vec<_>>(),
+            vec!["imply", "when", "preference", "or"]
+        );
+        let domain::action::Action::Simple(action) = &domain.actions[0] else {
+            unreachable!("Expected a simple action");
+        };
+        assert_eq!(
+            action.precondition,
+            Some(Expression::And(vec![
+                atom("imply", &["?x", "?y"]),
+                Expression::Or(vec![]),
+                Expression::Or(vec![atom("when", &["?x"]), atom("preference", &["?y"])]),
+            ]))
+        );
+        assert_eq!(domain.validate(), Ok(()));
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.objects.iter().map(|object| object.name.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(problem.init, vec![
+            atom("imply", &["a", "b"]),
+            atom("when", &["c"]),
+            atom("preference", &["a"]),
+        ]);
+        assert_eq!(
+            problem.goal,
+            Expression::And(vec![
+                atom("imply", &["c", "b"]),
+                Expression::Not(Box::new(atom("when", &["a"]))),
+            ])
+        );
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+
+        // `and` and `not` are only predicate names, not object or type names
+        let keyword_objects = problem_example.replace("(:objects a b c)", "(:objects a not c)");
+        assert!(Problem::parse(keyword_objects.as_str().into()).is_err());
+        let keyword_type = domain_example.replace("(:requirements :strips)", "(:requirements :strips :typing) (:types and)");
+        assert!(Domain::parse(keyword_type.as_str().into()).is_err());
+    }
+
+    #[test]
+    fn test_keywords_as_object_and_type_names() {
+        let domain_example = r"
+        (define (domain keywords)
+            (:requirements :strips :typing)
+            (:types within always - object)
+            (:predicates (on ?x - within ?y - always))
+            (:action when
+                :parameters (?x - within ?y - always)
+                :precondition (on ?x ?y)
+                :effect (not (on ?x ?y))
+            )
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(
+            domain.types.iter().map(|type_| type_.name.as_str()).collect::<Vec<_>>(),
+            vec!["within", "always"]
+        );
+        assert_eq!(domain.actions[0].name(), "when");
+        assert_eq!(domain.actions[0].parameters()[0].type_, Type::from("within"));
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+
+        let problem_example = r"
+        (define (problem keywords)
+            (:domain keywords)
+            (:objects sometime or - within exists - always)
+            (:init (on sometime exists))
+            (:goal (and (on or exists) (= or or)))
+            (:metric minimize (total-time))
+        )";
+        let problem = Problem::parse(problem_example.into()).expect("Failed to parse problem");
+        assert_eq!(
+            problem.objects.iter().map(|object| object.name.as_str()).collect::<Vec<_>>(),
+            vec!["sometime", "or", "exists"]
+        );
+        assert_eq!(Problem::parse(problem.to_pddl().as_str().into()), Ok(problem));
+    }
+
+    #[test]
+    fn test_duplicate_action() {
+        let mut domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        domain
+            .duplicate_action("pick-up", "pick-up-carefully")
+            .expect("Failed to duplicate action");
+        let original = domain.get_action("pick-up").expect("Missing original action");
+        let copy = domain.get_action("pick-up-carefully").expect("Missing duplicated action");
+        assert_eq!(copy.parameters(), original.parameters());
+        assert_eq!(copy.precondition(), original.precondition());
+        assert_eq!(domain.actions.last(), Some(copy));
+        assert_eq!(
+            domain.duplicate_action("pick-up", "PICK-UP-CAREFULLY"),
+            Err(ParserError::DuplicateAction("PICK-UP-CAREFULLY".into()))
+        );
+        assert_eq!(
+            domain.duplicate_action("juggle", "juggle-twice"),
+            Err(ParserError::UnknownAction("juggle".into()))
+        );
+        assert_eq!(Domain::parse(domain.to_pddl().as_str().into()), Ok(domain));
+    }
+
+    #[test]
+    fn test_record_spans() {
+        let domain_example = "(define (domain spans)
+            (:requirements :strips)
+            (:predicates (at-home ?r) (away ?r))
+            (:action leave
+                :parameters (?r)
+                :precondition (at-home ?r)
+                :effect (and (away ?r) (not (at-home ?r))))
+        )";
+        let options = ParseOptions {
+            record_spans: true,
+            ..ParseOptions::default()
+        };
+        let domain = Domain::parse_with_options(domain_example.into(), &options).expect("Failed to parse domain");
+        let domain::action::Action::Simple(action) = &domain.actions[0] else {
+            unreachable!("Expected a simple action");
+        };
+        let span = action.span.clone().expect("Missing action span");
+        assert!(domain_example[span.clone()].starts_with("(:action leave"));
+        assert!(domain_example[span].ends_with("(not (at-home ?r))))"));
+        let spans = domain
+            .predicates
+            .iter()
+            .map(|predicate| predicate.span.clone().map(|span| &domain_example[span]))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec![Some("(at-home ?r)"), Some("(away ?r)")]);
+
+        let unrecorded = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        assert_eq!(unrecorded.predicates[0].span, None);
+        assert_eq!(unrecorded, domain);
+        let mut actions = std::collections::HashSet::new();
+        actions.insert(unrecorded.actions[0].clone());
+        assert!(actions.contains(&domain.actions[0]));
+    }
+
+    #[test]
+    fn test_ground_action() {
+        let domain = Domain::parse(include_str!("../tests/domain.pddl").into()).expect("Failed to parse domain");
+        let action = domain
+            .get_action("pick-up")
+            .and_then(domain::action::Action::as_simple)
+            .expect("Missing action");
+        let bindings = [("?arm", "robot1"), ("?cupcake", "cake1"), ("?loc", "table")]
+            .into_iter()
+            .map(|(variable, object)| (variable.to_string(), object.to_string()))
+            .collect::<HashMap<_, _>>();
+        let grounded = action.ground(&bindings);
+        assert_eq!(
+            grounded.parameters.iter().map(|parameter| parameter.name.as_str()).collect::<Vec<_>>(),
+            vec!["robot1", "cake1", "table"]
+        );
+        assert_eq!(grounded.parameters[0].type_, action.parameters[0].type_);
+        fn atoms(expression: &Expression) -> Vec<(&str, Vec<&str>)> {
+            expression
+                .atoms()
+                .map(|(name, parameters)| (name, parameters.iter().map(Parameter::as_str).collect()))
+                .collect()
+        }
+        assert_eq!(
+            atoms(&grounded.effect),
+            vec![
+                ("on", vec!["cake1", "table"]),
+                ("holding", vec!["robot1", "cake1"]),
+                ("arm-empty", vec![]),
+            ]
+        );
+        let precondition = grounded.precondition.as_ref().expect("Missing precondition");
+        assert_eq!(
+            atoms(precondition),
+            vec![
+                ("on", vec!["robot1", "table"]),
+                ("on", vec!["cake1", "table"]),
+                ("arm-empty", vec![]),
+            ]
+        );
+        assert!(grounded.effect.free_variables().is_empty());
+        for expression in [precondition, &grounded.effect] {
+            let (_, reparsed) =
+                Expression::parse_expression(expression.to_pddl().as_str().into()).expect("Failed to reparse expression");
+            assert_eq!(&reparsed, expression);
+        }
+
+        let partial = action.ground(&[("?arm".to_string(), "robot1".to_string())].into_iter().collect());
+        assert_eq!(
+            atoms(&partial.effect),
+            vec![
+                ("on", vec!["?cupcake", "?loc"]),
+                ("holding", vec!["robot1", "?cupcake"]),
+                ("arm-empty", vec![]),
+            ]
+        );
+
+        let domain_example = "(define (domain moves)
+            (:requirements :strips :equality :numeric-fluents)
+            (:predicates (road ?x ?y))
+            (:functions (distance ?x ?y))
+            (:action move
+                :parameters (?x ?y)
+                :precondition (and (not (= ?x ?y)) (<= (distance ?x ?y) ?duration))
+                :effect (road ?x ?y))
+        )";
+        let domain = Domain::parse(domain_example.into()).expect("Failed to parse domain");
+        let action = domain.actions[0].as_simple().expect("Expected a simple action");
+        let bindings = [("?x", "a"), ("?y", "b")]
+            .into_iter()
+            .map(|(variable, object)| (variable.to_string(), object.to_string()))
+            .collect::<HashMap<_, _>>();
+        let grounded = action.ground(&bindings);
+        let term = |name: &str| Box::new(Expression::Term(name.into()));
+        assert_eq!(
+            grounded.precondition,
+            Some(Expression::And(vec![
+                Expression::Not(Box::new(Expression::BinaryOp(BinaryOp::Equal, term("a"), term("b")))),
+                Expression::BinaryOp(
+                    BinaryOp::LessThanOrEqual,
+                    Box::new(Expression::Atom {
+                        name: "distance".into(),
+                        parameters: vec!["a".into(), "b".into()],
+                    }),
+                    term("?duration")
+                ),
+            ]))
+        );
+        let precondition = grounded.precondition.expect("Missing precondition");
+        assert_eq!(precondition.to_pddl(), "(and (not (= a b)) (<= (distance a b) ?duration))");
+        let (_, reparsed) =
+            Expression::parse_expression(precondition.to_pddl().as_str().into()).expect("Failed to reparse precondition");
+        assert_eq!(reparsed, precondition);
+        assert_eq!(precondition.free_variables(), vec!["?duration".to_string()]);
+    }
 }
@@ -0,0 +1,29 @@
+use std::collections::HashSet;
+
+use crate::domain::requirement::Requirement;
+
+/// Options controlling how PDDL is parsed by the `parse_with_options` methods.
+///
+/// The default options are strict, which is what `parse` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Tolerate common deviations from the grammar instead of failing: skip the sections that are not modelled by the library (e.g. `(:bounds ...)`) and the initial facts that cannot be parsed, and unwrap a goal wrapped in a redundant pair of parentheses.
+    pub lenient: bool,
+    /// Keep the comments that document the declarations (e.g. [`crate::domain::typed_predicate::TypedPredicate::doc`]) instead of discarding them.
+    pub preserve_comments: bool,
+    /// Record the source range of the actions and predicates (e.g. [`crate::domain::simple_action::SimpleAction::span`]), for tools that map the AST back to the source, such as editors.
+    pub record_spans: bool,
+    /// The requirements whose features may be used, along with the requirements they imply (e.g. `:adl` allows `(or ...)`). Using the syntax of a feature whose requirements are not allowed (e.g. `(when ...)` without `:conditional-effects`) is an error, whatever the domain declares. `None` allows every requirement, in which case a feature that is not supported by the parser still fails to parse. Defaults to [`Requirement::supported`].
+    pub allowed_requirements: Option<HashSet<Requirement>>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            preserve_comments: false,
+            record_spans: false,
+            allowed_requirements: Some(Requirement::supported()),
+        }
+    }
+}
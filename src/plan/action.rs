@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 
 use super::durative_action::DurativeAction;
 use super::simple_action::SimpleAction;
+use crate::domain::domain::Domain;
+use crate::domain::parameter::Parameter;
+use crate::domain::typing::Type;
 use crate::error::ParserError;
 use crate::lexer::TokenStream;
 
@@ -29,13 +32,30 @@ impl Action {
     }
 
     /// Get the parameters of the action. This is the same as the parameters of the simple or durative action.
-    pub fn parameters(&self) -> &[crate::domain::parameter::Parameter] {
+    pub fn parameters(&self) -> &[Parameter] {
         match self {
             Self::Simple(action) => &action.parameters,
             Self::Durative(action) => &action.parameters,
         }
     }
 
+    /// Get the parameters of the action along with their types. The types are taken positionally from the parameters of the action with the same name (ignoring case) in the domain.
+    ///
+    /// Returns `None` if the domain has no such action, or if the number of parameters does not match.
+    pub fn typed_parameters(&self, domain: &Domain) -> Option<Vec<(Parameter, Type)>> {
+        let action = domain.get_action(self.name())?;
+        if action.parameters().len() != self.parameters().len() {
+            return None;
+        }
+        Some(
+            self.parameters()
+                .iter()
+                .cloned()
+                .zip(action.parameters().iter().map(|parameter| parameter.type_.clone()))
+                .collect(),
+        )
+    }
+
     /// Get the precondition of the action. This is the same as the precondition of the simple or durative action.
     pub fn parse(input: TokenStream) -> IResult<TokenStream, Action, ParserError> {
         log::debug!("BEGIN > parse_actions {:?}", input.span());
@@ -42,16 +42,18 @@ impl DurativeAction {
         }
     }
 
-    /// Parse a durative action from a token stream.
+    /// Parse a durative action from a token stream. The timestamp and the duration can be written either as floating point numbers or as integers (e.g. `0: (act) [5]`).
+    ///
+    /// Whitespace is allowed (but not required) between every element, so `10.0: (act) [5.0]`, `10.0 : (act) [5.0]` and `10.0:(act)[5.0]` are all accepted.
     pub fn parse(input: TokenStream) -> IResult<TokenStream, Self, ParserError> {
         let (output, (timestamp, (name, parameters), duration)) = tuple((
-            terminated(tokens::float, Token::Colon),
+            terminated(tokens::number, Token::Colon),
             delimited(
                 Token::OpenParen,
                 pair(id, Parameter::parse_parameters),
                 Token::CloseParen,
             ),
-            delimited(Token::OpenBracket, tokens::float, Token::CloseBracket),
+            delimited(Token::OpenBracket, tokens::number, Token::CloseBracket),
         ))(input)?;
         Ok((output, Self::new(name, parameters, duration, timestamp)))
     }
@@ -2,6 +2,7 @@ use nom::multi::many0;
 use serde::{Deserialize, Serialize};
 
 use super::action::Action;
+use crate::domain::domain::Domain;
 use crate::error::ParserError;
 use crate::lexer::TokenStream;
 
@@ -14,13 +15,14 @@ pub struct Plan(pub Vec<Action>);
 impl Plan {
     /// Parse a plan from a token stream.
     ///
-    /// The plan must be a sequence of actions. The parser will fail if there are any tokens left after the plan.
+    /// The plan must be a sequence of actions. The parser will fail if there are any tokens left after the plan. Comments are ignored anywhere, so the statistics that planners append after the plan (e.g. `; Time 0.05`) are accepted.
     ///
     /// # Errors
     ///
     /// The parser will fail if there are any tokens left after the plan. It will also fail if the plan is empty or if any of the actions are invalid.
     pub fn parse(input: TokenStream) -> Result<Self, ParserError> {
-        let (output, items) = many0(Action::parse)(input)?;
+        let source = input.source();
+        let (output, items) = many0(Action::parse)(input).map_err(|e| ParserError::from(e).with_location(source))?;
         if !output.is_empty() {
             log::error!("Plan parser failed: {:?}", output.peek_n(10));
             return Err(ParserError::ExpectedEndOfInput);
@@ -28,10 +30,166 @@ impl Plan {
         Ok(Plan(items))
     }
 
+    /// Parse a plan from an owned string, such as the output of a planner. See [`Domain::parse_owned`].
+    pub fn parse_owned(source: String) -> Result<Self, ParserError> {
+        Self::parse(source.as_str().into())
+    }
+
+    /// Serialize the plan to JSON. See [`Domain::to_json`].
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize a plan from the JSON produced by [`Plan::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Parse a plan in the IPC / VAL `.soln` format.
+    ///
+    /// The steps are parsed as in [`Plan::parse`]. The summary comments `; Cost: N` and `; Makespan: N` (also accepted with `=` instead of `:`, case-insensitive) of the trailing comment block, i.e. the comments after the last step, are used to extract the declared cost of the plan. Comments elsewhere in the plan are ignored, and so are the values that are not numbers (e.g. `; Cost: unknown`). If both are present, the cost takes precedence over the makespan. If neither is present, the cost is `None`.
+    ///
+    /// # Errors
+    ///
+    /// The parser will fail if the steps are not a valid plan.
+    pub fn parse_soln(input: &str) -> Result<(Self, Option<f64>), ParserError> {
+        let plan = Self::parse(input.into())?;
+        let mut trailing = input
+            .lines()
+            .rev()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map_while(|line| line.strip_prefix(';'))
+            .collect::<Vec<_>>();
+        trailing.reverse();
+        let mut cost = None;
+        let mut makespan = None;
+        for comment in trailing {
+            let comment = comment.trim().to_ascii_lowercase();
+            let Some((key, value)) = comment.split_once([':', '=']) else {
+                continue;
+            };
+            let target = match key.trim() {
+                "cost" => &mut cost,
+                "makespan" => &mut makespan,
+                _ => continue,
+            };
+            if let Some(value) = value.split_whitespace().next().and_then(|value| value.parse::<f64>().ok()) {
+                *target = Some(value);
+            }
+        }
+        Ok((plan, cost.or(makespan)))
+    }
+
     /// Get an iterator over the actions in the plan.
     pub fn actions(&self) -> impl Iterator<Item = &Action> {
         self.0.iter()
     }
+
+    /// Append the actions of `other` at the end of the plan, e.g. to stitch subplans together. See also the `+` operator.
+    pub fn append(&mut self, other: Plan) {
+        self.0.extend(other.0);
+    }
+
+    /// Append the actions of `other` at the end of the plan, delaying its durative actions by `shift` (e.g. the makespan of this plan). Simple actions have no timestamp, so they are appended unchanged.
+    pub fn append_shifted(&mut self, other: Plan, shift: f64) {
+        self.0.extend(other.0.into_iter().map(|action| match action {
+            Action::Durative(mut action) => {
+                action.timestamp += shift;
+                Action::Durative(action)
+            },
+            Action::Simple(action) => Action::Simple(action),
+        }));
+    }
+
+    /// Compute the cost of the plan, as the sum of the constant costs of its actions (see [`crate::domain::action::Action::constant_cost`]). The actions are matched by name (ignoring case) with the actions of the domain.
+    ///
+    /// Returns `None` if an action of the plan is not in the domain, or if its cost is not constant.
+    pub fn compute_cost(&self, domain: &Domain) -> Option<f64> {
+        self.0
+            .iter()
+            .map(|action| {
+                domain
+                    .actions
+                    .iter()
+                    .find(|candidate| candidate.name().eq_ignore_ascii_case(action.name()))?
+                    .constant_cost()
+            })
+            .sum()
+    }
+
+    /// Get an iterator over the actions in the plan with the given name. Names are matched case-insensitively, like PDDL identifiers.
+    pub fn actions_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Action> {
+        self.0.iter().filter(move |action| action.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns `true` if the plans are the same up to the listing order of their durative actions.
+    ///
+    /// The consecutive durative actions are sorted by timestamp and grouped: each group starts at the earliest remaining action and contains the actions that start within `epsilon` of it. Each group must contain the same actions in both plans, in any order. Timestamps and durations are compared within `epsilon`. Simple actions are sequential, so they must appear at the same position in both plans.
+    pub fn equivalent(&self, other: &Plan, epsilon: f64) -> bool {
+        let groups = self.concurrent_groups(epsilon);
+        let other_groups = other.concurrent_groups(epsilon);
+        groups.len() == other_groups.len()
+            && groups
+                .iter()
+                .zip(&other_groups)
+                .all(|(group, other_group)| Self::same_actions(group, other_group, epsilon))
+    }
+
+    /// Split the plan into groups of durative actions that start within `epsilon` of the earliest action of the group. The consecutive durative actions are sorted by timestamp first, so the groups do not depend on their listing order. Every simple action is a group of its own.
+    fn concurrent_groups(&self, epsilon: f64) -> Vec<Vec<&Action>> {
+        let mut groups = Vec::new();
+        let mut durative = Vec::new();
+        for action in &self.0 {
+            match action {
+                Action::Durative(durative_action) => durative.push((durative_action.timestamp, action)),
+                Action::Simple(_) => {
+                    Self::group_by_timestamp(&mut durative, epsilon, &mut groups);
+                    groups.push(vec![action]);
+                },
+            }
+        }
+        Self::group_by_timestamp(&mut durative, epsilon, &mut groups);
+        groups
+    }
+
+    /// Sort the timestamped actions and move them to `groups`, starting a new group at each action that starts more than `epsilon` after the first action of the current group.
+    fn group_by_timestamp<'a>(actions: &mut Vec<(f64, &'a Action)>, epsilon: f64, groups: &mut Vec<Vec<&'a Action>>) {
+        actions.sort_by(|(timestamp, _), (other, _)| timestamp.total_cmp(other));
+        let mut group = Vec::new();
+        let mut group_start = 0.0;
+        for (timestamp, action) in actions.drain(..) {
+            if !group.is_empty() && timestamp - group_start > epsilon {
+                groups.push(std::mem::take(&mut group));
+            }
+            if group.is_empty() {
+                group_start = timestamp;
+            }
+            group.push(action);
+        }
+        if !group.is_empty() {
+            groups.push(group);
+        }
+    }
+
+    /// Returns `true` if the two groups contain the same actions, in any order.
+    fn same_actions(group: &[&Action], other: &[&Action], epsilon: f64) -> bool {
+        let mut unmatched = other.to_vec();
+        group.len() == other.len()
+            && group.iter().all(|action| {
+                let matching = unmatched.iter().position(|candidate| match (action, candidate) {
+                    (Action::Simple(action), Action::Simple(candidate)) => action == candidate,
+                    (Action::Durative(action), Action::Durative(candidate)) => {
+                        action.name == candidate.name
+                            && action.parameters == candidate.parameters
+                            && (action.timestamp - candidate.timestamp).abs() <= epsilon
+                            && (action.duration - candidate.duration).abs() <= epsilon
+                    },
+                    _ => false,
+                });
+                matching.map(|index| unmatched.swap_remove(index)).is_some()
+            })
+    }
 }
 
 impl IntoIterator for Plan {
@@ -48,3 +206,12 @@ impl FromIterator<Action> for Plan {
         Self(iter.into_iter().collect())
     }
 }
+
+impl std::ops::Add for Plan {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self.append(other);
+        self
+    }
+}
@@ -0,0 +1,18 @@
+//! ```
+//! use pddl_parser::prelude::*;
+//!
+//! let domain = Domain::parse("(define (domain example) (:requirements :strips) (:predicates (p)))".into())?;
+//! assert_eq!(domain.name, "example");
+//! assert_eq!(domain.requirements, vec![Requirement::Strips]);
+//! # Ok::<(), ParserError>(())
+//! ```
+
+pub use crate::domain::action::Action;
+pub use crate::domain::domain::Domain;
+pub use crate::domain::expression::Expression;
+pub use crate::domain::requirement::Requirement;
+pub use crate::domain::typed_parameter::TypedParameter;
+pub use crate::domain::typing::Type;
+pub use crate::error::ParserError;
+pub use crate::plan::plan::Plan;
+pub use crate::problem::Problem;
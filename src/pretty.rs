@@ -0,0 +1,26 @@
+use crate::domain::expression::Expression;
+
+/// Options controlling how PDDL is emitted by the `to_pddl_with_options` methods.
+///
+/// The default options emit everything in declaration order, which is what `to_pddl` does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Sort the constants of a domain by type and then by name, instead of declaration order.
+    pub sort_constants: bool,
+    /// Sort the objects of a problem by type and then by name, instead of declaration order.
+    pub sort_objects: bool,
+    /// Indent the nested logical expressions (e.g. the preconditions and effects of the actions, or the goal of a problem) by depth, with one sub-expression per line (see [`Expression::to_pddl_pretty`]), instead of writing each expression on a single line.
+    pub indent: bool,
+}
+
+impl PrettyOptions {
+    /// Convert an expression to PDDL, indented if [`PrettyOptions::indent`] is set.
+    pub(crate) fn expression(&self, expression: &Expression) -> String {
+        if self.indent {
+            expression.to_pddl_pretty()
+        }
+        else {
+            expression.to_pddl()
+        }
+    }
+}
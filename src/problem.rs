@@ -1,14 +1,23 @@
-use nom::combinator::opt;
+use std::collections::{HashMap, HashSet};
+
+use nom::branch::alt;
+use nom::combinator::{map, opt, peek};
 use nom::multi::{many0, many1};
-use nom::sequence::{delimited, pair, preceded, tuple};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom::IResult;
 use serde::{Deserialize, Serialize};
 
-use crate::domain::expression::Expression;
+use crate::domain::domain::Domain;
+use crate::domain::expression::{BinaryOp, Expression};
+use crate::domain::requirement::Requirement;
+use crate::domain::typed_parameter::TypedParameter;
 use crate::domain::typing::Type;
+use crate::domain::validate::ValidationError;
 use crate::error::ParserError;
-use crate::lexer::{Token, TokenStream};
-use crate::tokens::id;
+use crate::lexer::{escape, Token, TokenStream};
+use crate::options::ParseOptions;
+use crate::pretty::PrettyOptions;
+use crate::tokens::{id, integer, lenient, parse_description, requires, skip_balanced};
 
 /// A PDDL object
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -21,12 +30,85 @@ pub struct Object {
 }
 
 impl Object {
+    /// Create a new object with the given name and type.
+    pub fn new(name: impl Into<String>, type_: impl Into<Type>) -> Self {
+        Self {
+            name: name.into(),
+            type_: type_.into(),
+        }
+    }
+
     /// Convert a typed object to a PDDL format. That is `name - type`.
     pub fn to_pddl(&self) -> String {
         format!("{} - {}", self.name, self.type_.to_pddl())
     }
 }
 
+impl From<(&str, &str)> for Object {
+    fn from((name, type_): (&str, &str)) -> Self {
+        Self::new(name, type_)
+    }
+}
+
+/// An element of the `:init` section, which is skipped in lenient mode if it cannot be parsed.
+enum InitElement {
+    Fact(Expression),
+    Skipped(String),
+}
+
+/// A PDDL metric, from a `(:metric minimize|maximize <expression>)` section
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Metric {
+    /// Minimize the value of the expression
+    Minimize(Expression),
+    /// Maximize the value of the expression
+    Maximize(Expression),
+}
+
+impl Metric {
+    /// Parse a metric section.
+    pub fn parse_metric(input: TokenStream) -> IResult<TokenStream, Metric, ParserError> {
+        log::debug!("BEGIN > parse_metric {:?}", input.span());
+        let expression = || {
+            alt((
+                Expression::parse_expression,
+                map(id, |name| Expression::Atom {
+                    name,
+                    parameters: vec![],
+                }),
+            ))
+        };
+        let (output, metric) = delimited(
+            Token::OpenParen,
+            preceded(
+                Token::Metric,
+                alt((
+                    map(preceded(Token::Minimize, expression()), Metric::Minimize),
+                    map(preceded(Token::Maximize, expression()), Metric::Maximize),
+                )),
+            ),
+            Token::CloseParen,
+        )(input)?;
+        log::debug!("END < parse_metric {:?}", output.span());
+        Ok((output, metric))
+    }
+
+    /// Returns the expression to optimize.
+    pub const fn expression(&self) -> &Expression {
+        match self {
+            Metric::Minimize(expression) | Metric::Maximize(expression) => expression,
+        }
+    }
+
+    /// Convert the metric to a PDDL `(:metric ...)` section.
+    pub fn to_pddl(&self) -> String {
+        match self {
+            Metric::Minimize(expression) => format!("(:metric minimize {})", expression.to_pddl()),
+            Metric::Maximize(expression) => format!("(:metric maximize {})", expression.to_pddl()),
+        }
+    }
+}
+
 /// A PDDL problem
 ///
 /// A problem is a description of a particular planning problem. It consists of a domain, a set of objects, an initial state, and a goal state.
@@ -36,6 +118,9 @@ pub struct Problem {
     pub name: String,
     /// The name of the domain of the problem
     pub domain: String,
+    /// The description of the problem, from the optional `(:description "...")` section
+    #[serde(default)]
+    pub description: Option<String>,
     /// The objects of the problem
     #[serde(default)]
     pub objects: Vec<Object>,
@@ -44,16 +129,53 @@ pub struct Problem {
     pub init: Vec<Expression>,
     /// The goal of the problem
     pub goal: Expression,
+    /// The constraints of the problem (PDDL 3)
+    #[serde(default)]
+    pub constraints: Option<Expression>,
+    /// The metrics of the problem, in declaration order. Most problems have at most one metric (see [`Problem::primary_metric`]).
+    #[serde(default)]
+    pub metrics: Vec<Metric>,
+    /// The planning horizon of the problem, from the optional `(:horizon n)` section used by some numeric planners
+    #[serde(default)]
+    pub horizon: Option<i64>,
+    /// The initial facts that were skipped because they could not be parsed (e.g. timed forms not modelled by the library, like `(at-end ...)`), as the text of their leading token. Facts are only skipped when parsing in lenient mode, and they are not emitted by [`Problem::to_pddl`].
+    #[serde(default)]
+    pub skipped_init: Vec<String>,
 }
 
 impl Problem {
     /// Parse a PDDL problem
     pub fn parse(input: TokenStream) -> Result<Self, ParserError> {
+        Self::parse_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parse a problem from an owned string, e.g. a problem generated at runtime. See [`Domain::parse_owned`].
+    pub fn parse_owned(source: String) -> Result<Self, ParserError> {
+        Self::parse(source.as_str().into())
+    }
+
+    /// Serialize the problem to JSON. See [`Domain::to_json`].
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserialize a problem from the JSON produced by [`Problem::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Parse a PDDL problem, using the given options to control the parsing.
+    ///
+    /// In lenient mode, the `(:bounds ...)` sections are skipped.
+    pub fn parse_with_options(input: TokenStream, options: &ParseOptions) -> Result<Self, ParserError> {
+        let source = input.source();
+        let input = input.with_options(options);
         let (output, problem) = delimited(
             Token::OpenParen,
             preceded(Token::Define, Problem::parse_problem),
             Token::CloseParen,
-        )(input)?;
+        )(input)
+        .map_err(|e| ParserError::from(e).with_location(source))?;
         if !output.is_empty() {
             return Err(ParserError::ExpectedEndOfInput);
         }
@@ -61,21 +183,33 @@ impl Problem {
     }
 
     fn parse_problem(input: TokenStream) -> IResult<TokenStream, Problem, ParserError> {
-        let (output, (name, domain, objects, init, goal)) = tuple((
+        let (
+            output,
+            (name, domain, description, objects, (init, skipped_init), goal, constraints, metrics, horizon),
+        ) = tuple((
             Problem::parse_name,
             Problem::parse_domain,
-            Problem::parse_objects,
-            Problem::parse_init,
+            opt(parse_description),
+            terminated(Problem::parse_objects, many0(Problem::skip_bounds)),
+            terminated(Problem::parse_init, many0(Problem::skip_bounds)),
             Problem::parse_goal,
+            opt(Problem::parse_constraints),
+            many0(Metric::parse_metric),
+            terminated(opt(Problem::parse_horizon), many0(Problem::skip_bounds)),
         ))(input)?;
         Ok((
             output,
             Problem {
                 name,
                 domain,
+                description,
                 objects,
                 init,
                 goal,
+                constraints,
+                metrics,
+                horizon,
+                skipped_init,
             },
         ))
     }
@@ -96,7 +230,10 @@ impl Problem {
             Token::OpenParen,
             preceded(
                 Token::Objects,
-                many0(pair(many1(id), opt(preceded(Token::Dash, Type::parse_type)))),
+                many0(pair(
+                    many1(id),
+                    opt(preceded(pair(Token::Dash, requires(&[Requirement::Typing])), Type::parse_type)),
+                )),
             ),
             Token::CloseParen,
         )(input)?;
@@ -115,38 +252,418 @@ impl Problem {
         Ok((output, objects))
     }
 
-    fn parse_init(input: TokenStream) -> IResult<TokenStream, Vec<Expression>, ParserError> {
+    /// Parse the initial state, returning the facts and the leading tokens of the skipped facts (see [`Problem::skipped_init`]).
+    fn parse_init(input: TokenStream) -> IResult<TokenStream, (Vec<Expression>, Vec<String>), ParserError> {
         log::debug!("BEGIN > parse_init {:?}", input.span());
-        let (output, init) = delimited(
+        let (output, elements) = delimited(
             Token::OpenParen,
-            preceded(Token::Init, many0(Expression::parse_expression)),
+            preceded(
+                Token::Init,
+                many0(alt((
+                    map(Expression::parse_expression, InitElement::Fact),
+                    map(Problem::skip_fact, InitElement::Skipped),
+                ))),
+            ),
             Token::CloseParen,
         )(input)?;
+        let mut init = Vec::new();
+        let mut skipped = Vec::new();
+        for element in elements {
+            match element {
+                InitElement::Fact(fact) => init.push(fact),
+                InitElement::Skipped(keyword) => skipped.push(keyword),
+            }
+        }
         log::debug!("END < parse_init {:?}", output.span());
-        Ok((output, init))
+        Ok((output, (init, skipped)))
+    }
+
+    /// Skip an initial fact that cannot be parsed, returning its leading token. Facts are only skipped in lenient mode.
+    fn skip_fact(input: TokenStream) -> IResult<TokenStream, String, ParserError> {
+        let (input, ()) = lenient(input)?;
+        log::debug!("BEGIN > skip_fact {:?}", input.span());
+        let keyword = input
+            .peek_n(2)
+            .and_then(|tokens| tokens.into_iter().nth(1))
+            .map(|(_, keyword)| keyword.trim().to_string())
+            .unwrap_or_default();
+        let (output, _) = skip_balanced(input)?;
+        log::warn!("Skipped the initial fact starting with `{keyword}`");
+        log::debug!("END < skip_fact {:?}", output.span());
+        Ok((output, keyword))
     }
 
     fn parse_goal(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
         let (output, goal) = delimited(
             Token::OpenParen,
-            preceded(Token::Goal, Expression::parse_expression),
+            preceded(Token::Goal, alt((Expression::parse_expression, Problem::parse_wrapped_goal))),
             Token::CloseParen,
         )(input)?;
         Ok((output, goal))
     }
 
+    /// Parse a goal wrapped in a redundant pair of parentheses, e.g. `((and ...))`, unwrapping it. The extra layer is only accepted in lenient mode, otherwise a [`ParserError::RedundantParentheses`] failure is returned.
+    fn parse_wrapped_goal(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        let span = input.span();
+        let (output, goal) = delimited(Token::OpenParen, Expression::parse_expression, Token::CloseParen)(input)?;
+        if !output.options().lenient {
+            return Err(nom::Err::Failure(ParserError::RedundantParentheses(span)));
+        }
+        log::warn!("Unwrapped the redundant parentheses around the goal");
+        Ok((output, goal))
+    }
+
+    fn parse_constraints(input: TokenStream) -> IResult<TokenStream, Expression, ParserError> {
+        let (output, constraints) = delimited(
+            Token::OpenParen,
+            preceded(
+                pair(Token::Constraints, requires(&[Requirement::Constraints])),
+                Expression::parse_expression,
+            ),
+            Token::CloseParen,
+        )(input)?;
+        Ok((output, constraints))
+    }
+
+    fn parse_horizon(input: TokenStream) -> IResult<TokenStream, i64, ParserError> {
+        let (output, horizon) =
+            delimited(Token::OpenParen, preceded(Token::Horizon, integer), Token::CloseParen)(input)?;
+        Ok((output, horizon))
+    }
+
+    fn skip_bounds(input: TokenStream) -> IResult<TokenStream, (), ParserError> {
+        let (input, ()) = lenient(input)?;
+        log::debug!("BEGIN > skip_bounds {:?}", input.span());
+        let (output, _) = preceded(peek(pair(Token::OpenParen, Token::Bounds)), skip_balanced)(input)?;
+        log::debug!("END < skip_bounds {:?}", output.span());
+        Ok((output, ()))
+    }
+
+    /// Index the objects by the name of their declared type, in declaration order. Only the declared type is used, so an object is not listed under the supertypes of its type (see [`Domain::is_subtype`]). An object of an `either` type is listed under each of its types.
+    pub fn objects_by_type(&self) -> HashMap<String, Vec<&Object>> {
+        let mut objects: HashMap<String, Vec<&Object>> = HashMap::new();
+        for object in &self.objects {
+            for name in object.type_.names() {
+                objects.entry(name.clone()).or_default().push(object);
+            }
+        }
+        objects
+    }
+
+    /// Check that no object name is declared more than once, regardless of the types of the declarations.
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per duplicated object name, in order of first declaration.
+    pub fn validate_objects(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            let declarations = self.objects.iter().filter(|o| o.name == object.name).collect::<Vec<_>>();
+            let first = self.objects.iter().position(|o| o.name == object.name);
+            if declarations.len() > 1 && first == Some(i) {
+                errors.push(format!(
+                    "Object `{}` is declared {} times (with types {})",
+                    object.name,
+                    declarations.len(),
+                    declarations.iter().map(|o| o.type_.to_pddl()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Check that the problem can be used with the given domain, i.e. that:
+    ///
+    /// - its `(:domain ...)` reference is the name of the domain,
+    /// - the types of its objects are declared by the domain,
+    /// - every atom of its initial state, goal and constraints is a predicate or a function of the domain, with the declared number of arguments.
+    ///
+    /// Names are compared case-insensitively, like PDDL keywords.
+    ///
+    /// # Errors
+    ///
+    /// Returns all of the mismatches: the domain name first, then the undeclared types in order of declaration, then the atoms in the order they appear.
+    pub fn validate_against(&self, domain: &Domain) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        if !self.domain.eq_ignore_ascii_case(&domain.name) {
+            errors.push(ValidationError::DomainMismatch {
+                problem: self.name.clone(),
+                expected: domain.name.clone(),
+                found: self.domain.clone(),
+            });
+        }
+        let declared = |name: &str| {
+            name.eq_ignore_ascii_case("object") || domain.types.iter().any(|t| t.name.eq_ignore_ascii_case(name))
+        };
+        for object in &self.objects {
+            for name in object.type_.names().iter().filter(|name| !declared(name)) {
+                errors.push(ValidationError::UndeclaredType {
+                    name: name.clone(),
+                    context: format!("object `{}`", object.name),
+                });
+            }
+        }
+        let expressions = self.init.iter().chain(Some(&self.goal)).chain(&self.constraints);
+        for expression in expressions {
+            expression.visit(&mut |expression| {
+                let Expression::Atom { name, parameters } = expression
+                else {
+                    return;
+                };
+                match domain.get_predicate(name).or_else(|| domain.get_function(name)) {
+                    None => errors.push(ValidationError::ProblemUndeclaredPredicate {
+                        problem: self.name.clone(),
+                        predicate: name.clone(),
+                    }),
+                    Some(declaration) if declaration.parameters.len() != parameters.len() => {
+                        errors.push(ValidationError::ProblemArityMismatch {
+                            problem: self.name.clone(),
+                            predicate: name.clone(),
+                            expected: declaration.parameters.len(),
+                            found: parameters.len(),
+                        });
+                    },
+                    Some(_) => {},
+                }
+            });
+        }
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+
+    /// Find the equalities between objects (e.g. `(= ?x ?y)`) used by the goal and the constraints of the problem when the domain does not declare the `:equality` requirement. Each use is reported as a warning, see [`Domain::uses_equality_without_requirement`].
+    pub fn uses_equality_without_requirement(&self, domain: &Domain) -> Vec<String> {
+        if domain.declares_equality() {
+            return Vec::new();
+        }
+        let is_object = |name: &str| {
+            self.objects.iter().any(|o| o.name == name) || domain.constants.iter().any(|c| c.name == name)
+        };
+        Some(&self.goal)
+            .into_iter()
+            .chain(&self.constraints)
+            .flat_map(|expression| Domain::object_equalities(expression, is_object))
+            .map(|equality| {
+                format!(
+                    "Problem `{}` uses `{}` without the `:equality` requirement",
+                    self.name,
+                    equality.to_pddl()
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the hard goal of the problem, i.e. the goal without its preferences (PDDL 3 soft goals).
+    ///
+    /// If the goal is a conjunction, the preferences are removed from it, and a single remaining conjunct is returned on its own. Returns `None` if the goal only consists of preferences.
+    pub fn hard_goal(&self) -> Option<Expression> {
+        match &self.goal {
+            Expression::Preference(_, _) => None,
+            Expression::And(expressions) => {
+                let mut hard = expressions
+                    .iter()
+                    .filter(|expression| !matches!(expression, Expression::Preference(_, _)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                match hard.len() {
+                    0 if !expressions.is_empty() => None,
+                    1 => hard.pop(),
+                    _ => Some(Expression::And(hard)),
+                }
+            },
+            goal => Some(goal.clone()),
+        }
+    }
+
+    /// Returns the first metric of the problem, if any.
+    pub fn primary_metric(&self) -> Option<&Metric> {
+        self.metrics.first()
+    }
+
+    /// Returns `true` if the goal contains a numeric comparison (`=`, `<`, `>`, `<=` or `>=`), e.g. `(>= (score) 100)`.
+    ///
+    /// A comparison is numeric if one of its operands is a function application, a number or an arithmetic operation. Object equalities such as `(= ?a ?b)` are not numeric.
+    pub fn has_numeric_goal(&self) -> bool {
+        let is_numeric = |operand: &Expression| match operand {
+            Expression::Atom { .. } | Expression::Number(_) | Expression::Float(_) => true,
+            Expression::BinaryOp(op, _, _) => !op.is_comparison(),
+            _ => false,
+        };
+        let mut stack = vec![&self.goal];
+        while let Some(expression) = stack.pop() {
+            if let Expression::BinaryOp(op, lhs, rhs) = expression {
+                if op.is_comparison() && (is_numeric(lhs) || is_numeric(rhs)) {
+                    return true;
+                }
+            }
+            stack.extend(expression.children());
+        }
+        false
+    }
+
+    /// Merge the initial state of another problem into this one.
+    ///
+    /// The facts of `other` are appended after the facts of this problem, skipping the facts that are already present (compared by their PDDL representation). Numeric assignments (`(= (f ...) value)`) to the same fluent must agree, comparing numbers by value, so `(= (f) 10)` and `(= (f) 10.0)` are the same assignment.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParserError::ConflictingAssignment`] if both problems assign different values to the same fluent. In that case, this problem is left unchanged.
+    pub fn merge_init(&mut self, other: &Problem) -> Result<(), ParserError> {
+        let mut seen = self.init.iter().map(Expression::to_pddl).collect::<HashSet<_>>();
+        let mut assignments = self
+            .init
+            .iter()
+            .filter_map(Self::assignment)
+            .map(|(fluent, value)| (fluent.to_pddl(), value))
+            .collect::<HashMap<_, _>>();
+        let mut merged = Vec::new();
+        for fact in &other.init {
+            if let Some((fluent, value)) = Self::assignment(fact) {
+                let fluent = fluent.to_pddl();
+                match assignments.get(&fluent) {
+                    Some(previous) if !Self::same_value(previous, value) => {
+                        return Err(ParserError::ConflictingAssignment(fluent))
+                    },
+                    Some(_) => continue,
+                    None => {
+                        assignments.insert(fluent, value);
+                    },
+                }
+            }
+            if seen.insert(fact.to_pddl()) {
+                merged.push(fact.clone());
+            }
+        }
+        self.init.extend(merged);
+        Ok(())
+    }
+
+    fn assignment(fact: &Expression) -> Option<(&Expression, &Expression)> {
+        match fact {
+            Expression::BinaryOp(BinaryOp::Equal, fluent, value) => Some((fluent, value)),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if both values are equal, comparing integers and floats numerically.
+    #[allow(clippy::cast_precision_loss)]
+    fn same_value(value1: &Expression, value2: &Expression) -> bool {
+        let number = |value: &Expression| match value {
+            Expression::Number(n) => Some(*n as f64),
+            Expression::Float(f) => Some(f.0),
+            _ => None,
+        };
+        match (number(value1), number(value2)) {
+            (Some(n1), Some(n2)) => n1 == n2,
+            _ => value1 == value2,
+        }
+    }
+
+    /// Expand the quantified initial facts of the problem into ground facts.
+    ///
+    /// Each `(forall (?x - t) body)` entry of `:init` is instantiated once for every object of the problem (and constant of the domain) whose type is compatible with `t`, using the type hierarchy of the domain. Conjunctions are split into separate facts. The other facts are returned unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParserError::UnboundVariable`] if an expanded fact still contains a variable.
+    pub fn expand_quantified_init(&self, domain: &Domain) -> Result<Vec<Expression>, ParserError> {
+        let mut facts = Vec::new();
+        for fact in &self.init {
+            self.expand_fact(domain, fact, &mut facts)?;
+        }
+        Ok(facts)
+    }
+
+    fn expand_fact(&self, domain: &Domain, fact: &Expression, facts: &mut Vec<Expression>) -> Result<(), ParserError> {
+        match fact {
+            Expression::Forall(parameters, body) => {
+                for bindings in self.bindings(domain, parameters) {
+                    self.expand_fact(domain, &body.substitute(&bindings), facts)?;
+                }
+            },
+            Expression::And(expressions) => {
+                for expression in expressions {
+                    self.expand_fact(domain, expression, facts)?;
+                }
+            },
+            _ => {
+                let mut stack = vec![fact];
+                while let Some(expression) = stack.pop() {
+                    if let Expression::Atom { parameters, .. } = expression {
+                        if let Some(variable) = parameters.iter().find(|p| p.is_variable()) {
+                            return Err(ParserError::UnboundVariable(variable.to_string()));
+                        }
+                    }
+                    stack.extend(expression.children());
+                }
+                facts.push(fact.clone());
+            },
+        }
+        Ok(())
+    }
+
+    fn bindings(&self, domain: &Domain, parameters: &[TypedParameter]) -> Vec<HashMap<String, String>> {
+        let candidates = self
+            .objects
+            .iter()
+            .map(|o| (&o.name, &o.type_))
+            .chain(domain.constants.iter().map(|c| (&c.name, &c.type_)));
+        let mut bindings = vec![HashMap::new()];
+        for parameter in parameters {
+            let objects = candidates
+                .clone()
+                .filter(|(_, type_)| domain.is_type_compatible(type_, &parameter.type_))
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            bindings = bindings
+                .into_iter()
+                .flat_map(|binding| {
+                    objects.iter().map(move |object| {
+                        let mut binding = binding.clone();
+                        binding.insert(parameter.name.clone(), (*object).clone());
+                        binding
+                    })
+                })
+                .collect();
+        }
+        bindings
+    }
+
     /// Convert the problem to PDDL format (as a string) for writing to a file
     pub fn to_pddl(&self) -> String {
+        self.to_pddl_with_options(&PrettyOptions::default())
+    }
+
+    /// Convert the problem to PDDL format (as a string), using the given options to control the output.
+    pub fn to_pddl_with_options(&self, options: &PrettyOptions) -> String {
         let mut pddl = String::new();
 
         // Name and domain
         pddl.push_str(&format!("(define (problem {})\n", self.name));
         pddl.push_str(&format!("(:domain {})\n", self.domain));
 
+        // Description
+        if let Some(description) = &self.description {
+            pddl.push_str(&format!("(:description {})\n", escape(description)));
+        }
+
         // Objects
+        let mut objects = self.objects.iter().collect::<Vec<_>>();
+        if options.sort_objects {
+            objects.sort_by(|a, b| (&a.type_, &a.name).cmp(&(&b.type_, &b.name)));
+        }
         pddl.push_str(&format!(
             "(:objects\n{}\n)\n",
-            self.objects.iter().map(Object::to_pddl).collect::<Vec<_>>().join("\n")
+            objects.into_iter().map(Object::to_pddl).collect::<Vec<_>>().join("\n")
         ));
 
         // Init
@@ -156,7 +673,23 @@ impl Problem {
         ));
 
         // Goal
-        pddl.push_str(&format!("(:goal\n{}\n)\n", &self.goal.to_pddl()));
+        pddl.push_str(&format!("(:goal\n{}\n)\n", options.expression(&self.goal)));
+
+        // Constraints
+        if let Some(constraints) = &self.constraints {
+            pddl.push_str(&format!("(:constraints\n{}\n)\n", options.expression(constraints)));
+        }
+
+        // Metrics
+        for metric in &self.metrics {
+            pddl.push_str(&metric.to_pddl());
+            pddl.push('\n');
+        }
+
+        // Horizon
+        if let Some(horizon) = self.horizon {
+            pddl.push_str(&format!("(:horizon {horizon})\n"));
+        }
 
         // End
         pddl.push(')');
@@ -164,3 +697,9 @@ impl Problem {
         pddl
     }
 }
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pddl())
+    }
+}
@@ -1,20 +1,56 @@
-use nom::IResult;
+use nom::sequence::{delimited, preceded};
+use nom::{IResult, Parser};
 
+use crate::domain::requirement::Requirement;
 use crate::error::ParserError;
 use crate::lexer::{Token, TokenStream};
 
 /// Parse an identifier from the input stream. Identifiers are strings that do not start with a question mark.
 ///
+/// The keywords that only have a meaning at the head of a form are accepted as well, so they can name objects, types and actions: the `or` connective, the quantifier and conditional keywords (`exists`, `when`), `preference`, the metric directions (`minimize`, `maximize`) and the modal operators of constraints (e.g. `always`). Where a form may appear, it is tried before an identifier.
+///
 /// # Errors
 ///
-/// Returns an error if the next token is not an identifier.
+/// Returns an error if the next token is neither an identifier nor one of these keywords.
 pub fn id(i: TokenStream) -> IResult<TokenStream, String, ParserError> {
     match i.peek() {
         Some((Ok(Token::Id(s)), _)) => Ok((i.advance(), s)),
+        Some((
+            Ok(Token::Or
+            | Token::Exists
+            | Token::When
+            | Token::Preference
+            | Token::Minimize
+            | Token::Maximize
+            | Token::Always
+            | Token::Sometime
+            | Token::Within
+            | Token::AtMostOnce
+            | Token::SometimeAfter
+            | Token::SometimeBefore
+            | Token::AlwaysWithin
+            | Token::HoldDuring
+            | Token::HoldAfter),
+            s,
+        )) => Ok((i.advance(), s.to_string())),
         _ => Err(nom::Err::Error(ParserError::ExpectedIdentifier)),
     }
 }
 
+/// Parse the name of a predicate from the input stream, in an atom or a predicate declaration.
+///
+/// Besides identifiers (see [`id`]), the logical connectives `and` and `not` are accepted, so domains can use them as predicate names. The forms themselves are tried before atoms, so `(not (p))` is still a negation and `(not ?x)` is an atom named `not`. Other words that are not lexed as keywords (e.g. `imply`) are plain identifiers.
+///
+/// # Errors
+///
+/// Returns an error if the next token is neither an identifier nor one of these keywords.
+pub fn predicate_name(i: TokenStream) -> IResult<TokenStream, String, ParserError> {
+    match i.peek() {
+        Some((Ok(Token::And | Token::Not), s)) => Ok((i.advance(), s.to_string())),
+        _ => id(i),
+    }
+}
+
 /// Parse a variable from the input stream. Variables are identifiers that start with a question mark.
 ///
 /// # Errors
@@ -39,6 +75,20 @@ pub fn float(i: TokenStream) -> IResult<TokenStream, f64, ParserError> {
     }
 }
 
+/// Parse a number from the input stream. The number can be either a floating point number or an integer, which is converted to a floating point number.
+///
+/// # Errors
+///
+/// Returns an error if the next token is neither a floating point number nor an integer.
+#[allow(clippy::cast_precision_loss)]
+pub fn number(i: TokenStream) -> IResult<TokenStream, f64, ParserError> {
+    match i.peek() {
+        Some((Ok(Token::Float(f)), _)) => Ok((i.advance(), f)),
+        Some((Ok(Token::Integer(n)), _)) => Ok((i.advance(), n as f64)),
+        _ => Err(nom::Err::Error(ParserError::ExpectedFloat)),
+    }
+}
+
 /// Parse an integer from the input stream.
 ///
 /// # Errors
@@ -50,3 +100,90 @@ pub fn integer(i: TokenStream) -> IResult<TokenStream, i64, ParserError> {
         _ => Err(nom::Err::Error(ParserError::ExpectedInteger)),
     }
 }
+
+/// Parse a string literal from the input stream. The escape sequences are resolved.
+///
+/// # Errors
+///
+/// Returns an error if the next token is not a string literal.
+pub fn string(i: TokenStream) -> IResult<TokenStream, String, ParserError> {
+    match i.peek() {
+        Some((Ok(Token::StringLit(s)), _)) => Ok((i.advance(), s)),
+        _ => Err(nom::Err::Error(ParserError::ExpectedToken(
+            Token::StringLit(String::new()),
+            i.span(),
+            i.peek_n(30),
+        ))),
+    }
+}
+
+/// Parse a `(:description "...")` section from the input stream, as found in some extended domain and problem formats.
+///
+/// # Errors
+///
+/// Returns an error if the next tokens are not a description section.
+pub fn parse_description(i: TokenStream) -> IResult<TokenStream, String, ParserError> {
+    delimited(Token::OpenParen, preceded(Token::Description, string), Token::CloseParen)(i)
+}
+
+/// Succeed without consuming any token if the input stream is parsed in lenient mode (see [`crate::options::ParseOptions::lenient`]), to guard the parsers that skip input the library does not model.
+///
+/// # Errors
+///
+/// Returns an error in strict mode. The error carries no context, as it is meant to be discarded by an enclosing `alt` or `many0`.
+pub fn lenient(i: TokenStream) -> IResult<TokenStream, (), ParserError> {
+    if i.options().lenient {
+        Ok((i, ()))
+    }
+    else {
+        Err(nom::Err::Error(ParserError::ExpectedToken(Token::OpenParen, i.span(), None)))
+    }
+}
+
+/// Succeed without consuming any token if a feature may be used, i.e. if one of the `requirements` enabling it is allowed by the options of the input stream, directly or through a shorthand such as `:adl` (see [`crate::options::ParseOptions::allowed_requirements`] and [`Requirement::implied`]). It guards the syntax of the feature right after its keyword, e.g. `(or ...)`, so that the feature is rejected where it is used rather than where it is declared.
+///
+/// # Errors
+///
+/// Returns a [`ParserError::UnsupportedRequirement`] failure for the first requirement if none of them is allowed. The keyword has been read at that point, so there is no other way to parse the input.
+pub fn requires(requirements: &'static [Requirement]) -> impl Fn(TokenStream) -> IResult<TokenStream, (), ParserError> {
+    move |i| {
+        let allowed = i.options().allowed_requirements.as_ref().map_or(true, |allowed| {
+            allowed
+                .iter()
+                .flat_map(Requirement::implied)
+                .any(|requirement| requirements.contains(&requirement))
+        });
+        if allowed {
+            Ok((i, ()))
+        }
+        else {
+            Err(nom::Err::Failure(ParserError::UnsupportedRequirement(requirements[0].clone())))
+        }
+    }
+}
+
+/// Skip a parenthesized group from the input stream, including any nested groups. The tokens inside the group are not interpreted, so the group may contain keywords unknown to the library.
+///
+/// # Errors
+///
+/// Returns an error if the next token is not an open parenthesis or if the group is not closed.
+pub fn skip_balanced(i: TokenStream) -> IResult<TokenStream, (), ParserError> {
+    let (mut i, _) = Token::OpenParen.parse(i)?;
+    let mut depth = 1usize;
+    while depth > 0 {
+        match i.peek() {
+            Some((Ok(Token::OpenParen), _)) => depth += 1,
+            Some((Ok(Token::CloseParen), _)) => depth -= 1,
+            Some(_) => {},
+            None => {
+                return Err(nom::Err::Error(ParserError::ExpectedToken(
+                    Token::CloseParen,
+                    i.span(),
+                    None,
+                )))
+            },
+        }
+        i = i.advance();
+    }
+    Ok((i, ()))
+}